@@ -0,0 +1,19 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::ChipLimits;
+use yolol_runner::YololRunner;
+
+// Parse, compile and run any script, none of it may panic.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(source) = std::str::from_utf8(data) {
+        let mut runner = YololRunner::builder()
+            .limits(ChipLimits::GAME.unlimited())
+            .source(source)
+            .build()
+            .unwrap();
+        for _ in 0..20 {
+            runner.step();
+        }
+    }
+});
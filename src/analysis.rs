@@ -0,0 +1,222 @@
+use yolol_devices::value::YololValue;
+
+use crate::vm::Instruction;
+use crate::Code;
+
+/// What is known of the type of a value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    String,
+    Int(Bool),
+    Unknown,
+}
+
+impl Type {
+    pub fn of(value: &YololValue) -> Type {
+        match value {
+            YololValue::String(_) => Type::String,
+            YololValue::Int(v) if v.into() => Type::Int(Bool::True),
+            YololValue::Int(_) => Type::Int(Bool::False),
+        }
+    }
+
+    pub fn truth(self) -> Bool {
+        match self {
+            Type::String => Bool::False,
+            Type::Int(b) => b,
+            Type::Unknown => Bool::Unknown,
+        }
+    }
+
+    /// Get the type of a variable that holds `self` or `other`.
+    pub fn join(self, other: Type) -> Type {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Type::Int(_), Type::Int(_)) => Type::Int(Bool::Unknown),
+            _ => Type::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bool {
+    True,
+    False,
+    Unknown,
+}
+
+/// Types of the stack and of the variables before an instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    pub stack: Vec<Type>,
+    pub ram: Vec<Type>,
+}
+
+impl State {
+    /// Get the state reached from either `self` or `other`.
+    pub fn join(&self, other: &State) -> State {
+        let join = |a: &[Type], b: &[Type]| a.iter().zip(b).map(|(a, b)| a.join(*b)).collect();
+        State {
+            stack: join(&self.stack, &other.stack),
+            ram: join(&self.ram, &other.ram),
+        }
+    }
+
+    fn pop(&mut self) -> Type {
+        self.stack.pop().unwrap_or(Type::Unknown)
+    }
+}
+
+/// Types along a line, found by [`analyze`].
+#[derive(Debug, Clone)]
+pub struct Analysis {
+    /// State before every instruction, `None` for the ones never reached.
+    pub states: Vec<Option<State>>,
+    /// Instructions certain to fail, the line stops there.
+    pub fails: Vec<bool>,
+    /// State once the line ran to its end.
+    pub end: Option<State>,
+}
+
+/// Follow the types of the values of `line` when it starts with variables of
+/// the types of `ram`.
+///
+/// Jumps only go forward, so a single walk over the line sees every path
+/// reaching an instruction before the instruction itself.
+pub fn analyze(line: &Code, ram: &[Type]) -> Analysis {
+    let mut states: Vec<Option<State>> = vec![None; line.len() + 1];
+    let mut fails = vec![false; line.len()];
+    states[0] = Some(State {
+        stack: vec![],
+        ram: ram.to_vec(),
+    });
+
+    for (i, (inst, _)) in line.iter().enumerate() {
+        let mut state = match states[i].clone() {
+            Some(state) => state,
+            None => continue,
+        };
+        let mut targets = vec![];
+        match inst {
+            Instruction::Jump(rel) => targets.push(i + 1 + rel),
+            Instruction::JumpFalse(rel) => match state.pop().truth() {
+                Bool::True => targets.push(i + 1),
+                Bool::False => targets.push(i + 1 + rel),
+                Bool::Unknown => targets.extend([i + 1, i + 1 + rel]),
+            },
+            _ => {
+                if !step(inst, &mut state) {
+                    fails[i] = true;
+                } else if !matches!(inst, Instruction::Goto) {
+                    targets.push(i + 1);
+                }
+            }
+        }
+        for target in targets {
+            if let Some(slot) = states.get_mut(target) {
+                *slot = Some(match slot {
+                    Some(old) => old.join(&state),
+                    None => state.clone(),
+                });
+            }
+        }
+    }
+
+    let end = states.pop().flatten();
+    Analysis { states, fails, end }
+}
+
+/// Apply the effect of `inst` to `state`, `false` if it is certain to fail.
+pub fn step(inst: &Instruction, state: &mut State) -> bool {
+    let result = match inst {
+        Instruction::Dup => {
+            let t = state.pop();
+            state.stack.push(t);
+            t
+        }
+        Instruction::Pop => {
+            state.pop();
+            return true;
+        }
+        Instruction::PushValue(v) => Type::of(v),
+        Instruction::Push(a) => state.ram.get(*a).copied().unwrap_or(Type::Unknown),
+        Instruction::Store(a) => {
+            let t = state.pop();
+            if let Some(slot) = state.ram.get_mut(*a) {
+                *slot = t;
+            }
+            return true;
+        }
+        Instruction::SyntaxError => return false,
+        Instruction::Goto => return state.pop() != Type::String,
+        Instruction::Jump(_) | Instruction::JumpFalse(_) => return true,
+        Instruction::Or => {
+            let (a, b) = (state.pop(), state.pop());
+            Type::Int(match (a.truth(), b.truth()) {
+                (Bool::True, _) | (_, Bool::True) => Bool::True,
+                (Bool::False, Bool::False) => Bool::False,
+                _ => Bool::Unknown,
+            })
+        }
+        Instruction::And => {
+            let (a, b) = (state.pop(), state.pop());
+            Type::Int(match (a.truth(), b.truth()) {
+                (Bool::False, _) | (_, Bool::False) => Bool::False,
+                (Bool::True, Bool::True) => Bool::True,
+                _ => Bool::Unknown,
+            })
+        }
+        Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Gt
+        | Instruction::Lte
+        | Instruction::Gte => {
+            state.pop();
+            state.pop();
+            Type::Int(Bool::Unknown)
+        }
+        Instruction::Add | Instruction::Sub => match (state.pop(), state.pop()) {
+            (Type::String, _) | (_, Type::String) => Type::String,
+            (Type::Unknown, _) | (_, Type::Unknown) => Type::Unknown,
+            _ => Type::Int(Bool::Unknown),
+        },
+        Instruction::Mul | Instruction::Div | Instruction::Mod | Instruction::Exp => {
+            let (b, a) = (state.pop(), state.pop());
+            if a == Type::String || b == Type::String {
+                return false;
+            }
+            Type::Int(Bool::Unknown)
+        }
+        Instruction::Abs
+        | Instruction::Sqrt
+        | Instruction::Sin
+        | Instruction::Cos
+        | Instruction::Tan
+        | Instruction::Asin
+        | Instruction::Acos
+        | Instruction::Atan
+        | Instruction::Fac
+        | Instruction::Neg => {
+            if state.pop() == Type::String {
+                return false;
+            }
+            Type::Int(Bool::Unknown)
+        }
+        Instruction::Not => Type::Int(match state.pop() {
+            // `not` of a string is 0
+            Type::String => Bool::False,
+            t => match t.truth() {
+                Bool::True => Bool::False,
+                Bool::False => Bool::True,
+                Bool::Unknown => Bool::Unknown,
+            },
+        }),
+        Instruction::Inc | Instruction::Dec => match state.pop() {
+            Type::Int(_) => Type::Int(Bool::Unknown),
+            t => t,
+        },
+    };
+    state.stack.push(result);
+    true
+}
@@ -1,49 +1,327 @@
+use std::ops::Deref;
+
+use crate::diagnostic::Diagnostic;
+
+/// Byte range of a node within its line, `end` excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Get the 1-indexed column of the start of the span in `line`.
+    pub fn column(&self, line: &str) -> usize {
+        line.get(..self.start)
+            .map_or(self.start, |s| s.chars().count())
+            + 1
+    }
+}
+
+/// A value with the span of source it was parsed from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Spanned<T> {
+    pub node: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(node: T, span: Span) -> Self {
+        Self { node, span }
+    }
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.node
+    }
+}
+
+/// A statement or expression, the operands of every operator are in source
+/// order.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Tree {
     Empty,
-    Error,
+    /// Segment that failed to parse, with its source text.
+    Error(String),
     Comment(String),
+    /// Number in thousandths, like the fixed point numbers of the game.
     Numerical(i64),
     String(String),
-    LocalVariable(usize),
-    GlobalVariable(usize),
-    Assign(Box<Tree>, Box<Tree>),
-    AssignAdd(Box<Tree>, Box<Tree>),
-    AssignSub(Box<Tree>, Box<Tree>),
-    AssignMul(Box<Tree>, Box<Tree>),
-    AssignDiv(Box<Tree>, Box<Tree>),
-    AssignMod(Box<Tree>, Box<Tree>),
-    AssignExp(Box<Tree>, Box<Tree>),
-    IfThen(Box<Tree>, Vec<Tree>),
-    IfThenElse(Box<Tree>, Vec<Tree>, Vec<Tree>),
-    Goto(Box<Tree>),
-    Or(Box<Tree>, Box<Tree>),
-    And(Box<Tree>, Box<Tree>),
-    Eq(Box<Tree>, Box<Tree>),
-    Ne(Box<Tree>, Box<Tree>),
-    Lt(Box<Tree>, Box<Tree>),
-    Gt(Box<Tree>, Box<Tree>),
-    Lte(Box<Tree>, Box<Tree>),
-    Gte(Box<Tree>, Box<Tree>),
-    Add(Box<Tree>, Box<Tree>),
-    Sub(Box<Tree>, Box<Tree>),
-    Mul(Box<Tree>, Box<Tree>),
-    Div(Box<Tree>, Box<Tree>),
-    Mod(Box<Tree>, Box<Tree>),
-    Exp(Box<Tree>, Box<Tree>),
-    Abs(Box<Tree>),
-    Sqrt(Box<Tree>),
-    Sin(Box<Tree>),
-    Cos(Box<Tree>),
-    Tan(Box<Tree>),
-    Asin(Box<Tree>),
-    Acos(Box<Tree>),
-    Atan(Box<Tree>),
-    Not(Box<Tree>),
-    PreDec(Box<Tree>),
-    PreInc(Box<Tree>),
-    PostDec(Box<Tree>),
-    PostInc(Box<Tree>),
-    Neg(Box<Tree>),
-    Fac(Box<Tree>),
+    LocalVariable(String),
+    GlobalVariable(String),
+    Assign(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignAdd(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignSub(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignMul(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignDiv(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignMod(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    AssignExp(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    IfThen(Box<Spanned<Tree>>, Vec<Spanned<Tree>>),
+    IfThenElse(Box<Spanned<Tree>>, Vec<Spanned<Tree>>, Vec<Spanned<Tree>>),
+    Goto(Box<Spanned<Tree>>),
+    Or(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    And(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Eq(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Ne(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Lt(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Gt(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Lte(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Gte(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Add(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Sub(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Mul(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Div(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Mod(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Exp(Box<Spanned<Tree>>, Box<Spanned<Tree>>),
+    Abs(Box<Spanned<Tree>>),
+    Sqrt(Box<Spanned<Tree>>),
+    Sin(Box<Spanned<Tree>>),
+    Cos(Box<Spanned<Tree>>),
+    Tan(Box<Spanned<Tree>>),
+    Asin(Box<Spanned<Tree>>),
+    Acos(Box<Spanned<Tree>>),
+    Atan(Box<Spanned<Tree>>),
+    Not(Box<Spanned<Tree>>),
+    PreDec(Box<Spanned<Tree>>),
+    PreInc(Box<Spanned<Tree>>),
+    PostDec(Box<Spanned<Tree>>),
+    PostInc(Box<Spanned<Tree>>),
+    Neg(Box<Spanned<Tree>>),
+    Fac(Box<Spanned<Tree>>),
+}
+
+impl Tree {
+    /// Get the direct sub trees, in source order.
+    pub fn children(&self) -> Vec<&Spanned<Tree>> {
+        match self {
+            Tree::Empty
+            | Tree::Error(_)
+            | Tree::Comment(_)
+            | Tree::Numerical(_)
+            | Tree::String(_)
+            | Tree::LocalVariable(_)
+            | Tree::GlobalVariable(_) => vec![],
+            Tree::Assign(a, b)
+            | Tree::AssignAdd(a, b)
+            | Tree::AssignSub(a, b)
+            | Tree::AssignMul(a, b)
+            | Tree::AssignDiv(a, b)
+            | Tree::AssignMod(a, b)
+            | Tree::AssignExp(a, b)
+            | Tree::Or(a, b)
+            | Tree::And(a, b)
+            | Tree::Eq(a, b)
+            | Tree::Ne(a, b)
+            | Tree::Lt(a, b)
+            | Tree::Gt(a, b)
+            | Tree::Lte(a, b)
+            | Tree::Gte(a, b)
+            | Tree::Add(a, b)
+            | Tree::Sub(a, b)
+            | Tree::Mul(a, b)
+            | Tree::Div(a, b)
+            | Tree::Mod(a, b)
+            | Tree::Exp(a, b) => vec![&**a, &**b],
+            Tree::IfThen(p, t) => {
+                let mut v = vec![&**p];
+                v.extend(t);
+                v
+            }
+            Tree::IfThenElse(p, t, f) => {
+                let mut v = vec![&**p];
+                v.extend(t);
+                v.extend(f);
+                v
+            }
+            Tree::Goto(a)
+            | Tree::Abs(a)
+            | Tree::Sqrt(a)
+            | Tree::Sin(a)
+            | Tree::Cos(a)
+            | Tree::Tan(a)
+            | Tree::Asin(a)
+            | Tree::Acos(a)
+            | Tree::Atan(a)
+            | Tree::Not(a)
+            | Tree::PreDec(a)
+            | Tree::PreInc(a)
+            | Tree::PostDec(a)
+            | Tree::PostInc(a)
+            | Tree::Neg(a)
+            | Tree::Fac(a) => vec![&**a],
+        }
+    }
+}
+
+/// A parsed script, see [`parse_program`](crate::parse_program).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    /// Statements of every line, the segments that failed to parse are
+    /// [`Tree::Error`] nodes.
+    pub lines: Vec<Vec<Spanned<Tree>>>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Program {
+    /// Get the same program with every span reset, to compare the trees of
+    /// different sources.
+    pub fn without_spans(self) -> Program {
+        ClearSpans.fold_program(self)
+    }
+}
+
+impl Spanned<Tree> {
+    /// Get the same tree with every span reset.
+    pub fn without_spans(self) -> Self {
+        ClearSpans.fold_tree(self)
+    }
+}
+
+struct ClearSpans;
+
+impl Fold for ClearSpans {
+    fn fold_tree(&mut self, tree: Spanned<Tree>) -> Spanned<Tree> {
+        let tree = fold_tree(self, tree);
+        Spanned::new(tree.node, Span::default())
+    }
+}
+
+/// Walk a parsed script by reference.
+///
+/// Every method visits the children of its node by default, an
+/// implementation overriding one can call the matching `walk_` function to
+/// keep going down.
+pub trait Visitor {
+    fn visit_program(&mut self, program: &Program) {
+        walk_program(self, program)
+    }
+
+    fn visit_line(&mut self, line: &[Spanned<Tree>]) {
+        walk_line(self, line)
+    }
+
+    fn visit_tree(&mut self, tree: &Spanned<Tree>) {
+        walk_tree(self, tree)
+    }
+}
+
+pub fn walk_program<V: Visitor + ?Sized>(visitor: &mut V, program: &Program) {
+    for line in &program.lines {
+        visitor.visit_line(line);
+    }
+}
+
+pub fn walk_line<V: Visitor + ?Sized>(visitor: &mut V, line: &[Spanned<Tree>]) {
+    for tree in line {
+        visitor.visit_tree(tree);
+    }
+}
+
+pub fn walk_tree<V: Visitor + ?Sized>(visitor: &mut V, tree: &Spanned<Tree>) {
+    for child in tree.children() {
+        visitor.visit_tree(child);
+    }
+}
+
+/// Rebuild a parsed script by value.
+///
+/// Every method folds the children of its node by default, an implementation
+/// overriding one can call the matching `fold_` function to keep going down.
+pub trait Fold {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_line(&mut self, line: Vec<Spanned<Tree>>) -> Vec<Spanned<Tree>> {
+        fold_line(self, line)
+    }
+
+    fn fold_tree(&mut self, tree: Spanned<Tree>) -> Spanned<Tree> {
+        fold_tree(self, tree)
+    }
+}
+
+pub fn fold_program<F: Fold + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        lines: program
+            .lines
+            .into_iter()
+            .map(|line| folder.fold_line(line))
+            .collect(),
+        ..program
+    }
+}
+
+pub fn fold_line<F: Fold + ?Sized>(folder: &mut F, line: Vec<Spanned<Tree>>) -> Vec<Spanned<Tree>> {
+    line.into_iter()
+        .map(|tree| folder.fold_tree(tree))
+        .collect()
+}
+
+/// Fold the children of `tree`, keeping its span.
+pub fn fold_tree<F: Fold + ?Sized>(folder: &mut F, tree: Spanned<Tree>) -> Spanned<Tree> {
+    let mut one = |a: Box<Spanned<Tree>>| Box::new(folder.fold_tree(*a));
+    let node = match tree.node {
+        Tree::Empty => Tree::Empty,
+        Tree::Error(e) => Tree::Error(e),
+        Tree::Comment(c) => Tree::Comment(c),
+        Tree::Numerical(n) => Tree::Numerical(n),
+        Tree::String(s) => Tree::String(s),
+        Tree::LocalVariable(v) => Tree::LocalVariable(v),
+        Tree::GlobalVariable(v) => Tree::GlobalVariable(v),
+        Tree::Assign(a, b) => Tree::Assign(one(a), one(b)),
+        Tree::AssignAdd(a, b) => Tree::AssignAdd(one(a), one(b)),
+        Tree::AssignSub(a, b) => Tree::AssignSub(one(a), one(b)),
+        Tree::AssignMul(a, b) => Tree::AssignMul(one(a), one(b)),
+        Tree::AssignDiv(a, b) => Tree::AssignDiv(one(a), one(b)),
+        Tree::AssignMod(a, b) => Tree::AssignMod(one(a), one(b)),
+        Tree::AssignExp(a, b) => Tree::AssignExp(one(a), one(b)),
+        Tree::IfThen(p, t) => {
+            let p = one(p);
+            Tree::IfThen(p, folder.fold_line(t))
+        }
+        Tree::IfThenElse(p, t, f) => {
+            let p = one(p);
+            let t = folder.fold_line(t);
+            Tree::IfThenElse(p, t, folder.fold_line(f))
+        }
+        Tree::Goto(a) => Tree::Goto(one(a)),
+        Tree::Or(a, b) => Tree::Or(one(a), one(b)),
+        Tree::And(a, b) => Tree::And(one(a), one(b)),
+        Tree::Eq(a, b) => Tree::Eq(one(a), one(b)),
+        Tree::Ne(a, b) => Tree::Ne(one(a), one(b)),
+        Tree::Lt(a, b) => Tree::Lt(one(a), one(b)),
+        Tree::Gt(a, b) => Tree::Gt(one(a), one(b)),
+        Tree::Lte(a, b) => Tree::Lte(one(a), one(b)),
+        Tree::Gte(a, b) => Tree::Gte(one(a), one(b)),
+        Tree::Add(a, b) => Tree::Add(one(a), one(b)),
+        Tree::Sub(a, b) => Tree::Sub(one(a), one(b)),
+        Tree::Mul(a, b) => Tree::Mul(one(a), one(b)),
+        Tree::Div(a, b) => Tree::Div(one(a), one(b)),
+        Tree::Mod(a, b) => Tree::Mod(one(a), one(b)),
+        Tree::Exp(a, b) => Tree::Exp(one(a), one(b)),
+        Tree::Abs(a) => Tree::Abs(one(a)),
+        Tree::Sqrt(a) => Tree::Sqrt(one(a)),
+        Tree::Sin(a) => Tree::Sin(one(a)),
+        Tree::Cos(a) => Tree::Cos(one(a)),
+        Tree::Tan(a) => Tree::Tan(one(a)),
+        Tree::Asin(a) => Tree::Asin(one(a)),
+        Tree::Acos(a) => Tree::Acos(one(a)),
+        Tree::Atan(a) => Tree::Atan(one(a)),
+        Tree::Not(a) => Tree::Not(one(a)),
+        Tree::PreDec(a) => Tree::PreDec(one(a)),
+        Tree::PreInc(a) => Tree::PreInc(one(a)),
+        Tree::PostDec(a) => Tree::PostDec(one(a)),
+        Tree::PostInc(a) => Tree::PostInc(one(a)),
+        Tree::Neg(a) => Tree::Neg(one(a)),
+        Tree::Fac(a) => Tree::Fac(one(a)),
+    };
+    Spanned::new(node, tree.span)
 }
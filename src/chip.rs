@@ -0,0 +1,90 @@
+use crate::ast::Spanned;
+use crate::ast::Tree;
+use crate::limits::ChipLimits;
+
+/// Tier of the YOLOL chip a script runs on.
+///
+/// Basic chips only have arithmetic, comparisons and logic, advanced chips
+/// add `%`, `^`, `abs`, `sqrt` and `!`, professional chips add trigonometry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ChipKind {
+    Basic,
+    Advanced,
+    #[default]
+    Professional,
+}
+
+impl ChipKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChipKind::Basic => "basic",
+            ChipKind::Advanced => "advanced",
+            ChipKind::Professional => "professional",
+        }
+    }
+
+    /// Size limits of this tier of chip, used when the runner is not given
+    /// its own. Every tier of the game holds 20 lines of 70 characters.
+    pub fn limits(&self) -> ChipLimits {
+        match self {
+            ChipKind::Basic => ChipLimits::BASIC,
+            ChipKind::Advanced => ChipLimits::ADVANCED,
+            ChipKind::Professional => ChipLimits::PROFESSIONAL,
+        }
+    }
+
+    /// Get the lowest tier able to execute the operator at the root of `tree`.
+    pub fn required(tree: &Tree) -> ChipKind {
+        match tree {
+            Tree::Mod(_, _)
+            | Tree::AssignMod(_, _)
+            | Tree::Exp(_, _)
+            | Tree::AssignExp(_, _)
+            | Tree::Abs(_)
+            | Tree::Sqrt(_)
+            | Tree::Fac(_) => ChipKind::Advanced,
+            Tree::Sin(_)
+            | Tree::Cos(_)
+            | Tree::Tan(_)
+            | Tree::Asin(_)
+            | Tree::Acos(_)
+            | Tree::Atan(_) => ChipKind::Professional,
+            _ => ChipKind::Basic,
+        }
+    }
+
+    /// Get the operators of `tree` this tier cannot execute, in source order.
+    pub fn unsupported(&self, tree: &Spanned<Tree>) -> Vec<Spanned<&'static str>> {
+        let mut found = vec![];
+        self.collect_unsupported(tree, &mut found);
+        found
+    }
+
+    fn collect_unsupported(&self, tree: &Spanned<Tree>, found: &mut Vec<Spanned<&'static str>>) {
+        if Self::required(tree) > *self {
+            found.push(Spanned::new(operator(tree), tree.span));
+        }
+        for child in tree.children() {
+            self.collect_unsupported(child, found);
+        }
+    }
+}
+
+fn operator(tree: &Tree) -> &'static str {
+    match tree {
+        Tree::Mod(_, _) => "%",
+        Tree::AssignMod(_, _) => "%=",
+        Tree::Exp(_, _) => "^",
+        Tree::AssignExp(_, _) => "^=",
+        Tree::Abs(_) => "abs",
+        Tree::Sqrt(_) => "sqrt",
+        Tree::Fac(_) => "!",
+        Tree::Sin(_) => "sin",
+        Tree::Cos(_) => "cos",
+        Tree::Tan(_) => "tan",
+        Tree::Asin(_) => "asin",
+        Tree::Acos(_) => "acos",
+        Tree::Atan(_) => "atan",
+        _ => "",
+    }
+}
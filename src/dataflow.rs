@@ -0,0 +1,105 @@
+use yolol_devices::value::YololValue;
+
+use crate::analysis::analyze;
+use crate::analysis::Bool;
+use crate::analysis::State;
+use crate::analysis::Type;
+use crate::vm::Instruction;
+use crate::Code;
+
+/// Get the types the variables can have when each line starts, `None` for
+/// the lines no run reaches.
+///
+/// Lines are followed through their fall-through and their gotos until the
+/// types stop changing, a goto to a computed line can reach any line. Any
+/// device can write the `globals` between two lines, so they are never known
+/// when a line starts.
+pub fn entry_types(lines: &[Code], memory: usize, globals: &[usize]) -> Vec<Option<Vec<Type>>> {
+    let external = |mut ram: Vec<Type>| {
+        for adress in globals {
+            ram[*adress] = Type::Unknown;
+        }
+        ram
+    };
+
+    let mut entries: Vec<Option<Vec<Type>>> = vec![None; lines.len()];
+    if lines.is_empty() {
+        return entries;
+    }
+    // every variable starts at 0
+    entries[0] = Some(external(vec![Type::Int(Bool::False); memory]));
+    let mut todo = vec![0];
+    while let Some(line) = todo.pop() {
+        let entry = entries[line].clone().unwrap_or_default();
+        for (next, exit) in exits(&lines[line], line, lines.len(), &entry) {
+            let exit = external(exit);
+            let joined = match &entries[next] {
+                Some(old) => old.iter().zip(&exit).map(|(a, b)| a.join(*b)).collect(),
+                None => exit,
+            };
+            if entries[next].as_ref() != Some(&joined) {
+                entries[next] = Some(joined);
+                todo.push(next);
+            }
+        }
+    }
+    entries
+}
+
+/// Get the lines that can run after the line `n` of a program of `len`
+/// lines, with the types the variables can have when moving to them.
+fn exits(line: &Code, n: usize, len: usize, entry: &[Type]) -> Vec<(usize, Vec<Type>)> {
+    let analysis = analyze(line, entry);
+    let mut exits = vec![];
+    let next = (n + 1) % len;
+    if let Some(end) = analysis.end {
+        exits.push((next, end.ram));
+    }
+    for (i, (inst, _)) in line.iter().enumerate() {
+        let state = match &analysis.states[i] {
+            Some(state) => state,
+            None => continue,
+        };
+        if analysis.fails[i] || may_fail(inst, state) {
+            exits.push((next, state.ram.clone()));
+        }
+        if let Instruction::Goto = inst {
+            match i.checked_sub(1).map(|i| &line[i].0) {
+                Some(Instruction::PushValue(YololValue::Int(v))) => {
+                    // like the vm, out of range targets are clamped
+                    let v: i64 = v.into();
+                    let target = (v - 1).clamp(0, len as i64 - 1) as usize;
+                    exits.push((target, state.ram.clone()));
+                }
+                _ => exits.extend((0..len).map(|target| (target, state.ram.clone()))),
+            }
+        }
+    }
+    exits
+}
+
+/// Check if `inst` can raise a runtime error when run in `state`.
+fn may_fail(inst: &Instruction, state: &State) -> bool {
+    match inst {
+        Instruction::Goto => !matches!(state.stack.last(), Some(Type::Int(_))),
+        Instruction::Dup
+        | Instruction::Pop
+        | Instruction::PushValue(_)
+        | Instruction::Push(_)
+        | Instruction::Store(_)
+        | Instruction::Jump(_)
+        | Instruction::JumpFalse(_)
+        | Instruction::Or
+        | Instruction::And
+        | Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Gt
+        | Instruction::Lte
+        | Instruction::Gte
+        | Instruction::Add
+        | Instruction::Not
+        | Instruction::Inc => false,
+        _ => true,
+    }
+}
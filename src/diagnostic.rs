@@ -0,0 +1,105 @@
+use std::fmt::Display;
+
+use peg::error::ParseError;
+use peg::str::LineCol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// What a [`Diagnostic`] is about, for tools that handle some of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// Source the parser cannot read.
+    Syntax,
+    /// Line longer than the chip allows.
+    LineLength,
+    /// Script with more lines than the chip allows.
+    ScriptLength,
+    Other,
+}
+
+/// A problem found while parsing or compiling a script.
+///
+/// `line` and `column` are 1-indexed, a `column` of 0 means the whole line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub expected: Vec<String>,
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(file: Option<&str>, line: usize, column: usize, message: String) -> Self {
+        Self {
+            file: file.map(|s| s.to_string()),
+            line,
+            column,
+            expected: vec![],
+            severity: Severity::Error,
+            kind: DiagnosticKind::Other,
+            message,
+        }
+    }
+
+    pub fn warning(file: Option<&str>, line: usize, column: usize, message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(file, line, column, message)
+        }
+    }
+
+    /// Build a diagnostic from a failed parse of the 1-indexed `line`.
+    pub fn from_parse_error(file: Option<&str>, line: usize, err: &ParseError<LineCol>) -> Self {
+        let mut expected: Vec<String> = err.expected.tokens().map(|s| s.to_string()).collect();
+        expected.sort();
+        expected.dedup();
+        Self {
+            expected,
+            kind: DiagnosticKind::Syntax,
+            ..Self::error(
+                file,
+                line,
+                err.location.column,
+                format!("unexpected token, expected {}", err.expected),
+            )
+        }
+    }
+
+    pub fn with_kind(self, kind: DiagnosticKind) -> Self {
+        Self { kind, ..self }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}:{}:{}: {}",
+            self.severity,
+            self.file.as_deref().unwrap_or("<source>"),
+            self.line,
+            self.column,
+            self.message
+        )
+    }
+}
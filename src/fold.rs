@@ -0,0 +1,246 @@
+use yolol_devices::value::YololValue;
+
+use crate::ast::Span;
+use crate::ir::thousandths;
+use crate::vm::Instruction;
+use crate::vm::VM;
+use crate::Code;
+
+/// What is known of a value on the stack.
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Const(YololValue),
+    Number,
+    Unknown,
+}
+
+impl Kind {
+    fn is_number(&self) -> bool {
+        matches!(self, Kind::Number | Kind::Const(YololValue::Int(_)))
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            Kind::Const(YololValue::Int(v)) => v == &0.into(),
+            _ => false,
+        }
+    }
+}
+
+/// Largest number whose factorial is computed at compile time, the vm
+/// multiplies once per unit and 19! overflows the numbers of yolol-devices.
+const MAX_FOLDED_FACTORIAL: i64 = 18;
+
+/// Largest exponent, either sign, of a power computed at compile time.
+const MAX_FOLDED_EXPONENT: i64 = 64;
+
+/// Check that `inst` on the constants `operands` is cheap to compute and
+/// cannot overflow in yolol-devices, the others are left to runtime.
+fn foldable(inst: &Instruction, operands: &[&YololValue]) -> bool {
+    let int = |v: &YololValue| match v {
+        YololValue::Int(v) => Some(*v),
+        YololValue::String(_) => None,
+    };
+    match (inst, operands) {
+        (Instruction::Fac, [a]) => int(a).is_none_or(|a| i64::from(&a) <= MAX_FOLDED_FACTORIAL),
+        (Instruction::Exp, [_, b]) => {
+            int(b).is_none_or(|b| i64::from(&b).abs() <= MAX_FOLDED_EXPONENT)
+        }
+        (Instruction::Mod, [a, b]) => match (int(a), int(b)) {
+            // the remainder of yolol-devices, in checked arithmetic
+            (Some(a), Some(b)) => {
+                let (a, b) = (thousandths(&a), thousandths(&b));
+                b == 0
+                    || a.checked_div(b)
+                        .and_then(|d| d.checked_add((a > 0) as i64))
+                        .and_then(|d| b.checked_mul(d))
+                        .and_then(|m| a.checked_sub(m))
+                        .is_some()
+            }
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Run `code` on its own and get the value it leaves, `None` if it fails.
+fn eval(code: &[(Instruction, Span)]) -> Option<YololValue> {
+    let mut line: Vec<Instruction> = code.iter().map(|(i, _)| i.clone()).collect();
+    line.push(Instruction::Store(0));
+    let mut vm = VM::new(vec![line], 1);
+    vm.run().ok()?;
+    vm.variable(0).cloned()
+}
+
+/// Replace the operations on constants of a compiled line by their result,
+/// recording the span of every change in `changes`.
+///
+/// Constants are computed by the vm itself so they match the game, an
+/// operation that fails like `1/0` is kept to fail at runtime. Adding or
+/// subtracting zero is dropped when the other operand is a number, `x * 1`
+/// and `x / 1` are kept as they round huge numbers.
+pub fn fold(code: Code, changes: &mut Vec<(Span, &'static str)>) -> Code {
+    let mut targets = vec![false; code.len() + 1];
+    for (c, (inst, _)) in code.iter().enumerate() {
+        if let Instruction::Jump(rel) | Instruction::JumpFalse(rel) = inst {
+            if let Some(target) = targets.get_mut(c + 1 + rel) {
+                *target = true;
+            }
+        }
+    }
+
+    let mut out: Code = vec![];
+    // new index of every old instruction, to move the jumps
+    let mut map = vec![0; code.len() + 1];
+    let mut jumps = vec![];
+    // values on the stack and the index in `out` of the code computing them
+    let mut stack: Vec<(Kind, usize)> = vec![];
+    // nothing before a jump target can be folded with what follows it
+    let mut barrier = 0;
+
+    for (c, (inst, span)) in code.iter().enumerate() {
+        map[c] = out.len();
+        if targets[c] {
+            barrier = out.len();
+            for (kind, _) in stack.iter_mut() {
+                *kind = Kind::Unknown;
+            }
+        }
+        let start = out.len();
+        let kind = match inst {
+            Instruction::PushValue(v) => Some(Kind::Const(v.clone())),
+            Instruction::Push(_) => Some(Kind::Unknown),
+            Instruction::Dup => {
+                // both copies come from the code of the first one
+                let kind = match stack.last_mut() {
+                    Some((kind, _)) if kind.is_number() => Kind::Number,
+                    _ => Kind::Unknown,
+                };
+                if let Some(last) = stack.last_mut() {
+                    last.0 = kind.clone();
+                }
+                Some(kind)
+            }
+            Instruction::Pop | Instruction::Store(_) | Instruction::Goto => {
+                stack.pop();
+                None
+            }
+            Instruction::Jump(rel) | Instruction::JumpFalse(rel) => {
+                if let Instruction::JumpFalse(_) = inst {
+                    stack.pop();
+                }
+                jumps.push((out.len(), c + 1 + rel));
+                barrier = out.len() + 1;
+                None
+            }
+            Instruction::SyntaxError => None,
+            Instruction::Or
+            | Instruction::And
+            | Instruction::Eq
+            | Instruction::Ne
+            | Instruction::Lt
+            | Instruction::Gt
+            | Instruction::Lte
+            | Instruction::Gte
+            | Instruction::Add
+            | Instruction::Sub
+            | Instruction::Mul
+            | Instruction::Div
+            | Instruction::Mod
+            | Instruction::Exp => {
+                let (b, b_start) = stack.pop().unwrap_or((Kind::Unknown, start));
+                let (a, a_start) = stack.pop().unwrap_or((Kind::Unknown, start));
+                let number = match inst {
+                    Instruction::Add | Instruction::Sub => a.is_number() && b.is_number(),
+                    _ => true,
+                };
+                if a_start >= barrier {
+                    let constants = match (&a, &b) {
+                        (Kind::Const(a), Kind::Const(b)) => foldable(inst, &[a, b]),
+                        _ => false,
+                    };
+                    if constants {
+                        out.push((inst.clone(), *span));
+                        if let Some(v) = eval(&out[a_start..]) {
+                            out.truncate(a_start);
+                            out.push((Instruction::PushValue(v.clone()), *span));
+                            stack.push((Kind::Const(v), a_start));
+                            changes.push((*span, "is computed once"));
+                            continue;
+                        }
+                        out.pop();
+                    }
+                    let add = matches!(inst, Instruction::Add);
+                    let sub = matches!(inst, Instruction::Sub);
+                    if (add || sub) && a.is_number() && b.is_zero() {
+                        out.truncate(b_start);
+                        let change = if add {
+                            "adds zero to a number"
+                        } else {
+                            "subtracts zero from a number"
+                        };
+                        changes.push((*span, change));
+                        stack.push((Kind::Number, a_start));
+                        continue;
+                    }
+                    if add && a.is_zero() && b.is_number() {
+                        out.remove(a_start);
+                        changes.push((*span, "adds zero to a number"));
+                        stack.push((Kind::Number, a_start));
+                        continue;
+                    }
+                }
+                stack.push((if number { Kind::Number } else { Kind::Unknown }, a_start));
+                out.push((inst.clone(), *span));
+                continue;
+            }
+            Instruction::Abs
+            | Instruction::Sqrt
+            | Instruction::Sin
+            | Instruction::Cos
+            | Instruction::Tan
+            | Instruction::Asin
+            | Instruction::Acos
+            | Instruction::Atan
+            | Instruction::Not
+            | Instruction::Fac
+            | Instruction::Neg
+            | Instruction::Inc
+            | Instruction::Dec => {
+                let (a, a_start) = stack.pop().unwrap_or((Kind::Unknown, start));
+                if a_start >= barrier && matches!(&a, Kind::Const(a) if foldable(inst, &[a])) {
+                    out.push((inst.clone(), *span));
+                    if let Some(v) = eval(&out[a_start..]) {
+                        out.truncate(a_start);
+                        out.push((Instruction::PushValue(v.clone()), *span));
+                        stack.push((Kind::Const(v), a_start));
+                        changes.push((*span, "is computed once"));
+                        continue;
+                    }
+                    out.pop();
+                }
+                let number = match inst {
+                    Instruction::Inc | Instruction::Dec => a.is_number(),
+                    _ => true,
+                };
+                stack.push((if number { Kind::Number } else { Kind::Unknown }, a_start));
+                out.push((inst.clone(), *span));
+                continue;
+            }
+        };
+        if let Some(kind) = kind {
+            stack.push((kind, start));
+        }
+        out.push((inst.clone(), *span));
+    }
+    map[code.len()] = out.len();
+
+    for (at, target) in jumps {
+        let rel = map[target] - at - 1;
+        match &mut out[at].0 {
+            Instruction::Jump(r) | Instruction::JumpFalse(r) => *r = rel,
+            _ => (),
+        }
+    }
+    out
+}
@@ -0,0 +1,318 @@
+use std::fmt::Display;
+
+use crate::ast::Program;
+use crate::ast::Spanned;
+use crate::ast::Tree;
+
+/// How much space the formatter puts between tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Spacing {
+    /// Spaces around binary operators, assignments and between statements.
+    #[default]
+    Spaced,
+    /// Only the spaces the parser needs to read the source back, numbers
+    /// lose their leading zero.
+    Compact,
+}
+
+/// Format every line of `program`.
+///
+/// Parsing the result gives back the same trees, only their spans differ.
+pub fn format_program(program: &Program, spacing: Spacing) -> String {
+    program
+        .lines
+        .iter()
+        .map(|line| format_line(line, spacing))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format the statements of a line.
+pub fn format_line(line: &[Spanned<Tree>], spacing: Spacing) -> String {
+    let mut writer = Writer::new(spacing);
+    writer.block(line);
+    writer.out
+}
+
+/// Format a single statement or expression.
+pub fn format_tree(tree: &Tree, spacing: Spacing) -> String {
+    let mut writer = Writer::new(spacing);
+    writer.stmt(tree);
+    writer.out
+}
+
+impl Display for Tree {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format_tree(self, Spacing::Spaced))
+    }
+}
+
+/// Binding strength of the operator at the root of `tree`, following the
+/// levels of the grammar.
+fn precedence(tree: &Tree) -> u8 {
+    match tree {
+        Tree::Or(_, _) => 1,
+        Tree::And(_, _) => 2,
+        Tree::Not(_) => 3,
+        Tree::Eq(_, _)
+        | Tree::Ne(_, _)
+        | Tree::Lt(_, _)
+        | Tree::Gt(_, _)
+        | Tree::Lte(_, _)
+        | Tree::Gte(_, _) => 4,
+        Tree::Add(_, _) | Tree::Sub(_, _) => 5,
+        Tree::Mul(_, _) | Tree::Div(_, _) | Tree::Mod(_, _) => 6,
+        Tree::Exp(_, _) => 7,
+        Tree::Abs(_)
+        | Tree::Sqrt(_)
+        | Tree::Sin(_)
+        | Tree::Cos(_)
+        | Tree::Tan(_)
+        | Tree::Asin(_)
+        | Tree::Acos(_)
+        | Tree::Atan(_)
+        | Tree::Neg(_) => 8,
+        Tree::Fac(_) => 9,
+        _ => 10,
+    }
+}
+
+fn binary(tree: &Tree) -> Option<(&Tree, &'static str, &Tree)> {
+    let (l, op, r) = match tree {
+        Tree::Or(l, r) => (l, "or", r),
+        Tree::And(l, r) => (l, "and", r),
+        Tree::Eq(l, r) => (l, "==", r),
+        Tree::Ne(l, r) => (l, "!=", r),
+        Tree::Lt(l, r) => (l, "<", r),
+        Tree::Gt(l, r) => (l, ">", r),
+        Tree::Lte(l, r) => (l, "<=", r),
+        Tree::Gte(l, r) => (l, ">=", r),
+        Tree::Add(l, r) => (l, "+", r),
+        Tree::Sub(l, r) => (l, "-", r),
+        Tree::Mul(l, r) => (l, "*", r),
+        Tree::Div(l, r) => (l, "/", r),
+        Tree::Mod(l, r) => (l, "%", r),
+        Tree::Exp(l, r) => (l, "^", r),
+        _ => return None,
+    };
+    Some((l, op, r))
+}
+
+fn prefix(tree: &Tree) -> Option<(&'static str, &Tree)> {
+    let (op, r) = match tree {
+        Tree::Not(r) => ("not", r),
+        Tree::Abs(r) => ("abs", r),
+        Tree::Sqrt(r) => ("sqrt", r),
+        Tree::Sin(r) => ("sin", r),
+        Tree::Cos(r) => ("cos", r),
+        Tree::Tan(r) => ("tan", r),
+        Tree::Asin(r) => ("asin", r),
+        Tree::Acos(r) => ("acos", r),
+        Tree::Atan(r) => ("atan", r),
+        Tree::Neg(r) => ("-", r),
+        Tree::PreInc(r) => ("++", r),
+        Tree::PreDec(r) => ("--", r),
+        _ => return None,
+    };
+    Some((op, r))
+}
+
+fn assignment(tree: &Tree) -> Option<(&Tree, &'static str, &Tree)> {
+    let (l, op, r) = match tree {
+        Tree::Assign(l, r) => (l, "=", r),
+        Tree::AssignAdd(l, r) => (l, "+=", r),
+        Tree::AssignSub(l, r) => (l, "-=", r),
+        Tree::AssignMul(l, r) => (l, "*=", r),
+        Tree::AssignDiv(l, r) => (l, "/=", r),
+        Tree::AssignMod(l, r) => (l, "%=", r),
+        Tree::AssignExp(l, r) => (l, "^=", r),
+        _ => return None,
+    };
+    Some((l, op, r))
+}
+
+/// Write a number of thousandths without trailing zeros, `short` drops the
+/// zero before the decimal point too.
+pub fn number(v: i64, short: bool) -> String {
+    let sign = if v < 0 { "-" } else { "" };
+    let v = v.unsigned_abs();
+    let (int, frac) = (v / 1000, v % 1000);
+    if frac == 0 {
+        format!("{}{}", sign, int)
+    } else if int == 0 && short {
+        let frac = format!("{:03}", frac);
+        format!("{}.{}", sign, frac.trim_end_matches('0'))
+    } else {
+        let frac = format!("{:03}", frac);
+        format!("{}{}.{}", sign, int, frac.trim_end_matches('0'))
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+struct Writer {
+    out: String,
+    spacing: Spacing,
+    // the last token is a prefix minus, which must not touch a number
+    neg: bool,
+    // the last token is a segment that failed to parse, which runs up to the
+    // next space
+    error: bool,
+}
+
+impl Writer {
+    fn new(spacing: Spacing) -> Self {
+        Self {
+            out: String::new(),
+            spacing,
+            neg: false,
+            error: false,
+        }
+    }
+
+    /// Append `token`, separated from the previous one if they would be read
+    /// back as a single token.
+    fn token(&mut self, token: &str) {
+        if let (Some(a), Some(b)) = (self.out.chars().last(), token.chars().next()) {
+            let glued = ((is_word(a) || a == ':') && (is_word(b) || b == '.'))
+                || (a == '-' && b == '-')
+                || (a == '+' && b == '+')
+                || (a == '!' && b == '=')
+                || (self.neg && (b.is_ascii_digit() || b == '.'))
+                || (self.error && a != ' ');
+            if glued {
+                self.out.push(' ');
+            }
+        }
+        self.out.push_str(token);
+        self.neg = false;
+        self.error = false;
+    }
+
+    /// Separate two tokens in the spaced style.
+    fn space(&mut self) {
+        if self.spacing == Spacing::Spaced && !self.out.is_empty() && !self.out.ends_with(' ') {
+            self.out.push(' ');
+        }
+    }
+
+    fn block(&mut self, block: &[Spanned<Tree>]) {
+        for (i, stmt) in block.iter().enumerate() {
+            self.space();
+            // an expression starting with an operator would continue the
+            // previous statement
+            let starts_with_operator = i > 0
+                && !matches!(stmt.node, Tree::Comment(_))
+                && assignment(&stmt.node).is_none()
+                && format_tree(&stmt.node, Spacing::Compact).starts_with(['-', '+']);
+            if starts_with_operator {
+                self.token("(");
+                self.stmt(stmt);
+                self.token(")");
+            } else {
+                self.stmt(stmt);
+            }
+        }
+    }
+
+    fn stmt(&mut self, tree: &Tree) {
+        match tree {
+            Tree::Empty => (),
+            Tree::Error(e) => {
+                self.error = true;
+                self.token(e);
+                self.error = true;
+            }
+            Tree::Comment(c) => {
+                self.token("//");
+                self.out.push_str(c);
+            }
+            Tree::IfThen(p, t) => {
+                self.token("if");
+                self.space();
+                self.expr(p, 0);
+                self.space();
+                self.token("then");
+                self.block(t);
+                self.space();
+                self.token("end");
+            }
+            Tree::IfThenElse(p, t, f) => {
+                self.token("if");
+                self.space();
+                self.expr(p, 0);
+                self.space();
+                self.token("then");
+                self.block(t);
+                self.space();
+                self.token("else");
+                self.block(f);
+                self.space();
+                self.token("end");
+            }
+            Tree::Goto(e) => {
+                self.token("goto");
+                self.space();
+                self.expr(e, 0);
+            }
+            _ => match assignment(tree) {
+                Some((l, op, r)) => {
+                    self.expr(l, 10);
+                    self.space();
+                    self.token(op);
+                    self.space();
+                    self.expr(r, 0);
+                }
+                None => self.expr(tree, 0),
+            },
+        }
+    }
+
+    /// Write `tree`, in parentheses if its operator binds less than `min`.
+    fn expr(&mut self, tree: &Tree, min: u8) {
+        let level = precedence(tree);
+        if level < min {
+            self.token("(");
+            self.expr(tree, 0);
+            self.token(")");
+            return;
+        }
+        if let Some((l, op, r)) = binary(tree) {
+            // every binary operator is left associative
+            self.expr(l, level);
+            self.space();
+            self.token(op);
+            self.space();
+            self.expr(r, level + 1);
+        } else if let Some((op, r)) = prefix(tree) {
+            self.token(op);
+            if op.starts_with(is_word) {
+                self.space();
+            }
+            self.neg = op == "-";
+            self.expr(r, level);
+        } else {
+            match tree {
+                Tree::Fac(l) => {
+                    self.expr(l, level);
+                    self.token("!");
+                }
+                Tree::PostInc(l) => {
+                    self.expr(l, 10);
+                    self.token("++");
+                }
+                Tree::PostDec(l) => {
+                    self.expr(l, 10);
+                    self.token("--");
+                }
+                Tree::Numerical(v) => self.token(&number(*v, self.spacing == Spacing::Compact)),
+                Tree::String(s) => self.token(&format!("\"{}\"", s)),
+                Tree::LocalVariable(v) => self.token(v),
+                Tree::GlobalVariable(v) => self.token(&format!(":{}", v)),
+                _ => self.stmt(tree),
+            }
+        }
+    }
+}
@@ -0,0 +1,845 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use yolol_devices::value::YololInt;
+use yolol_devices::value::YololValue;
+
+use crate::analysis::step;
+use crate::analysis::Bool;
+use crate::analysis::State;
+use crate::analysis::Type;
+use crate::ast::Span;
+use crate::ast::Spanned;
+use crate::ast::Tree;
+use crate::format::number;
+use crate::symbols::SymbolTable;
+use crate::vm::Instruction;
+use crate::Code;
+
+/// A value of an [`IrLine`], defined once by the instruction of the same
+/// index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValueId(pub usize);
+
+/// A basic block of an [`IrLine`], blocks are laid out in the order of their
+/// index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BlockId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Lte,
+    Gte,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Exp,
+}
+
+impl BinOp {
+    pub fn instruction(self) -> Instruction {
+        match self {
+            BinOp::Or => Instruction::Or,
+            BinOp::And => Instruction::And,
+            BinOp::Eq => Instruction::Eq,
+            BinOp::Ne => Instruction::Ne,
+            BinOp::Lt => Instruction::Lt,
+            BinOp::Gt => Instruction::Gt,
+            BinOp::Lte => Instruction::Lte,
+            BinOp::Gte => Instruction::Gte,
+            BinOp::Add => Instruction::Add,
+            BinOp::Sub => Instruction::Sub,
+            BinOp::Mul => Instruction::Mul,
+            BinOp::Div => Instruction::Div,
+            BinOp::Mod => Instruction::Mod,
+            BinOp::Exp => Instruction::Exp,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            BinOp::Or => "or",
+            BinOp::And => "and",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Gt => ">",
+            BinOp::Lte => "<=",
+            BinOp::Gte => ">=",
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Mod => "%",
+            BinOp::Exp => "^",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnOp {
+    Abs,
+    Sqrt,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Not,
+    Fac,
+    Neg,
+    Inc,
+    Dec,
+}
+
+impl UnOp {
+    pub fn instruction(self) -> Instruction {
+        match self {
+            UnOp::Abs => Instruction::Abs,
+            UnOp::Sqrt => Instruction::Sqrt,
+            UnOp::Sin => Instruction::Sin,
+            UnOp::Cos => Instruction::Cos,
+            UnOp::Tan => Instruction::Tan,
+            UnOp::Asin => Instruction::Asin,
+            UnOp::Acos => Instruction::Acos,
+            UnOp::Atan => Instruction::Atan,
+            UnOp::Not => Instruction::Not,
+            UnOp::Fac => Instruction::Fac,
+            UnOp::Neg => Instruction::Neg,
+            UnOp::Inc => Instruction::Inc,
+            UnOp::Dec => Instruction::Dec,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            UnOp::Abs => "abs",
+            UnOp::Sqrt => "sqrt",
+            UnOp::Sin => "sin",
+            UnOp::Cos => "cos",
+            UnOp::Tan => "tan",
+            UnOp::Asin => "asin",
+            UnOp::Acos => "acos",
+            UnOp::Atan => "atan",
+            UnOp::Not => "not",
+            UnOp::Fac => "!",
+            UnOp::Neg => "-",
+            UnOp::Inc => "++",
+            UnOp::Dec => "--",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Const(YololValue),
+    /// Read the variable of a slot.
+    Load(usize),
+    /// Apply an operator to its operands, evaluated in source order.
+    Binary(BinOp, ValueId, ValueId),
+    Unary(UnOp, ValueId),
+    /// Write a value to the variable of a slot.
+    Store(usize, ValueId),
+    /// Drop the value of an expression used as a statement.
+    Discard(ValueId),
+}
+
+impl Op {
+    /// Get the operands in the order they are evaluated.
+    pub fn operands(&self) -> Vec<ValueId> {
+        match self {
+            Op::Const(_) | Op::Load(_) => vec![],
+            Op::Binary(_, a, b) => vec![*a, *b],
+            Op::Unary(_, a) | Op::Store(_, a) | Op::Discard(a) => vec![*a],
+        }
+    }
+
+    fn instruction(&self) -> Instruction {
+        match self {
+            Op::Const(v) => Instruction::PushValue(v.clone()),
+            Op::Load(adress) => Instruction::Push(*adress),
+            Op::Binary(op, _, _) => op.instruction(),
+            Op::Unary(op, _) => op.instruction(),
+            Op::Store(adress, _) => Instruction::Store(*adress),
+            Op::Discard(_) => Instruction::Pop,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Inst {
+    pub op: Op,
+    /// Type of the value defined, `None` for the effects defining no value.
+    pub ty: Option<Type>,
+    pub span: Span,
+}
+
+/// How the line goes on once a block ran.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    Jump(BlockId, Span),
+    /// Continue with `then` if `cond` is true, else with `otherwise`.
+    Branch {
+        cond: ValueId,
+        then: BlockId,
+        otherwise: BlockId,
+        span: Span,
+    },
+    /// Leave the line for the line of the value.
+    Goto(ValueId, Span),
+    /// A segment that failed to parse, the line stops with an error.
+    Abort(Span),
+    /// Move to the next line.
+    End,
+}
+
+/// Where a line can go from a block, see [`IrLine::successors`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edge {
+    Block(BlockId),
+    NextLine,
+    Goto(ValueId),
+    /// A runtime error stops the line.
+    Abort,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    /// Instructions run in order, every operand is defined before.
+    pub insts: Vec<ValueId>,
+    /// Values whose instruction, or whose use by the exit, may raise a
+    /// runtime error, each an edge to the abort of the line.
+    pub aborts: Vec<ValueId>,
+    pub exit: Exit,
+}
+
+/// A compiled line in SSA form, every value is defined by a single
+/// instruction and variables are only read and written through slots.
+///
+/// The compiler builds it from the trees of a line and lowers it to the
+/// bytecode the optimization passes work on. The blocks of an `if` built from
+/// source only hold statements, a value used in another block is kept in a
+/// temporary slot once lowered.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct IrLine {
+    pub insts: Vec<Inst>,
+    pub blocks: Vec<Block>,
+}
+
+impl IrLine {
+    pub fn inst(&self, value: ValueId) -> &Inst {
+        &self.insts[value.0]
+    }
+
+    /// Check if the instruction of `value` can raise a runtime error, given
+    /// the types of its operands.
+    pub fn may_abort(&self, value: ValueId) -> bool {
+        let int = |v: &ValueId| matches!(self.inst(*v).ty, Some(Type::Int(_)));
+        match &self.inst(value).op {
+            Op::Const(_) | Op::Load(_) | Op::Store(_, _) | Op::Discard(_) => false,
+            Op::Binary(BinOp::Sub, a, b) => !(int(a) && int(b)),
+            Op::Binary(BinOp::Mul | BinOp::Div | BinOp::Mod | BinOp::Exp, _, _) => true,
+            Op::Binary(_, _, _) => false,
+            Op::Unary(UnOp::Not | UnOp::Inc, _) => false,
+            Op::Unary(UnOp::Neg | UnOp::Dec, a) => !int(a),
+            Op::Unary(_, _) => true,
+        }
+    }
+
+    /// Get the edges leaving `block`, a block with [`Block::aborts`] has an
+    /// [`Edge::Abort`].
+    pub fn successors(&self, block: BlockId) -> Vec<Edge> {
+        let block = &self.blocks[block.0];
+        let mut edges = match block.exit {
+            Exit::Jump(target, _) => vec![Edge::Block(target)],
+            Exit::Branch {
+                then, otherwise, ..
+            } => vec![Edge::Block(then), Edge::Block(otherwise)],
+            Exit::Goto(target, _) => vec![Edge::Goto(target)],
+            Exit::Abort(_) => vec![Edge::Abort],
+            Exit::End => vec![Edge::NextLine],
+        };
+        if !edges.contains(&Edge::Abort) && !block.aborts.is_empty() {
+            edges.push(Edge::Abort);
+        }
+        edges
+    }
+
+    /// Count the uses of every value, by instructions and exits.
+    pub fn uses(&self) -> Vec<usize> {
+        let mut uses = vec![0; self.insts.len()];
+        for inst in &self.insts {
+            for v in inst.op.operands() {
+                uses[v.0] += 1;
+            }
+        }
+        for block in &self.blocks {
+            match block.exit {
+                Exit::Branch { cond: v, .. } | Exit::Goto(v, _) => uses[v.0] += 1,
+                _ => (),
+            }
+        }
+        uses
+    }
+
+    /// Lower the line to the bytecode of the vm.
+    ///
+    /// Values are kept on the stack when each operand is on top of it when
+    /// used, a value used again right away is duplicated and a value nobody
+    /// uses is popped. The others are kept in temporary slots of `symbols`,
+    /// and constants are pushed again at every use.
+    pub fn lower(&self, symbols: &mut SymbolTable) -> Result<Code, LowerError> {
+        let mut spilled = vec![false; self.insts.len()];
+        loop {
+            match self.lower_with(&spilled, symbols) {
+                Err(LowerError::Unscheduled(v)) if !spilled[v.0] => spilled[v.0] = true,
+                result => return result,
+            }
+        }
+    }
+
+    /// Lower the line keeping the `spilled` values out of the stack.
+    fn lower_with(&self, spilled: &[bool], symbols: &mut SymbolTable) -> Result<Code, LowerError> {
+        let mut lowering = Lowering {
+            line: self,
+            remaining: self.uses(),
+            spilled,
+            slots: vec![None; self.insts.len()],
+            temps: 0,
+            symbols,
+            stack: vec![],
+            code: vec![],
+        };
+        let mut starts = Vec::with_capacity(self.blocks.len() + 1);
+        // jumps to point at the start of a block once every block is laid out
+        let mut patches: Vec<(usize, BlockId)> = vec![];
+
+        for (b, block) in self.blocks.iter().enumerate() {
+            starts.push(lowering.code.len());
+            for v in &block.insts {
+                lowering.define(*v)?;
+            }
+            match block.exit {
+                Exit::Jump(target, span) => {
+                    if target.0 != b + 1 {
+                        patches.push((lowering.code.len(), target));
+                        lowering.code.push((Instruction::Jump(0), span));
+                    }
+                }
+                Exit::Branch {
+                    cond,
+                    then,
+                    otherwise,
+                    span,
+                } => {
+                    // the vm only jumps when the condition is false
+                    if then.0 != b + 1 {
+                        return Err(LowerError::Layout(then));
+                    }
+                    lowering.take(&[cond], span)?;
+                    patches.push((lowering.code.len(), otherwise));
+                    lowering.code.push((Instruction::JumpFalse(0), span));
+                }
+                Exit::Goto(target, span) => {
+                    lowering.take(&[target], span)?;
+                    lowering.code.push((Instruction::Goto, span));
+                }
+                Exit::Abort(span) => lowering.code.push((Instruction::SyntaxError, span)),
+                Exit::End => {
+                    if b + 1 != self.blocks.len() {
+                        patches.push((lowering.code.len(), BlockId(self.blocks.len())));
+                        lowering.code.push((Instruction::Jump(0), Span::default()));
+                    }
+                }
+            }
+            if let Some(v) = lowering.stack.last() {
+                return Err(LowerError::Unscheduled(*v));
+            }
+        }
+        starts.push(lowering.code.len());
+
+        let mut code = lowering.code;
+        for (i, target) in patches {
+            let rel = starts[target.0]
+                .checked_sub(i + 1)
+                .ok_or(LowerError::Layout(target))?;
+            match &mut code[i].0 {
+                Instruction::Jump(r) | Instruction::JumpFalse(r) => *r = rel,
+                _ => (),
+            }
+        }
+        Ok(code)
+    }
+}
+
+impl Display for IrLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (b, block) in self.blocks.iter().enumerate() {
+            writeln!(f, "b{}:", b)?;
+            for v in &block.insts {
+                let inst = self.inst(*v);
+                let op = match &inst.op {
+                    Op::Const(YololValue::Int(n)) => number(thousandths(n), false),
+                    Op::Const(YololValue::String(s)) => format!("\"{}\"", s),
+                    Op::Load(adress) => format!("[{}]", adress),
+                    Op::Binary(op, a, b) => format!("v{} {} v{}", a.0, op.symbol(), b.0),
+                    Op::Unary(op, a) => format!("{} v{}", op.symbol(), a.0),
+                    Op::Store(adress, a) => format!("[{}] = v{}", adress, a.0),
+                    Op::Discard(a) => format!("discard v{}", a.0),
+                };
+                match inst.ty {
+                    Some(ty) => writeln!(f, "  v{} = {} : {}", v.0, op, type_name(ty))?,
+                    None => writeln!(f, "  {}", op)?,
+                }
+            }
+            if !block.aborts.is_empty() {
+                let values: Vec<String> =
+                    block.aborts.iter().map(|v| format!("v{}", v.0)).collect();
+                writeln!(f, "  may abort on {}", values.join(", "))?;
+            }
+            match block.exit {
+                Exit::Jump(target, _) => writeln!(f, "  jump b{}", target.0)?,
+                Exit::Branch {
+                    cond,
+                    then,
+                    otherwise,
+                    ..
+                } => writeln!(f, "  branch v{} b{} b{}", cond.0, then.0, otherwise.0)?,
+                Exit::Goto(target, _) => writeln!(f, "  goto v{}", target.0)?,
+                Exit::Abort(_) => writeln!(f, "  abort")?,
+                Exit::End => writeln!(f, "  end")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Get the fixed point value of `n`, yolol-devices only gives its whole part.
+pub(crate) fn thousandths(n: &YololInt) -> i64 {
+    let whole: i64 = n.into();
+    // less than one, so a thousand times it is a whole number
+    let decimals = n - &YololInt::from(whole);
+    let decimals: i64 = (&(&decimals * &YololInt::from(1000))).into();
+    whole * 1000 + decimals
+}
+
+fn type_name(ty: Type) -> &'static str {
+    match ty {
+        Type::String => "string",
+        Type::Int(Bool::True) => "true",
+        Type::Int(Bool::False) => "false",
+        Type::Int(Bool::Unknown) => "number",
+        Type::Unknown => "unknown",
+    }
+}
+
+/// A line that [`IrLine::lower`] cannot turn into stack bytecode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LowerError {
+    /// A value is used before the instruction defining it ran.
+    Unscheduled(ValueId),
+    /// A block is laid out where the jumps to it cannot reach it, jumps only
+    /// go forward and a branch goes on with the next block.
+    Layout(BlockId),
+}
+
+impl Display for LowerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LowerError::Unscheduled(v) => write!(f, "value v{} is used before it is defined", v.0),
+            LowerError::Layout(b) => write!(f, "block b{} cannot be reached by a jump", b.0),
+        }
+    }
+}
+
+impl Error for LowerError {}
+
+struct Lowering<'a> {
+    line: &'a IrLine,
+    /// Uses left of every value kept on the stack.
+    remaining: Vec<usize>,
+    /// Values kept out of the stack.
+    spilled: &'a [bool],
+    /// Slot of every spilled value once defined.
+    slots: Vec<Option<usize>>,
+    /// Temporaries used so far.
+    temps: usize,
+    symbols: &'a mut SymbolTable,
+    stack: Vec<ValueId>,
+    code: Code,
+}
+
+impl Lowering<'_> {
+    /// Compute `v`, leaving it on the stack or storing it to a temporary.
+    fn define(&mut self, v: ValueId) -> Result<(), LowerError> {
+        let inst = self.line.inst(v);
+        let spilled = self.spilled[v.0];
+        if spilled && matches!(inst.op, Op::Const(_)) {
+            return Ok(());
+        }
+        self.take(&inst.op.operands(), inst.span)?;
+        self.code.push((inst.op.instruction(), inst.span));
+        if inst.ty.is_none() {
+            return Ok(());
+        }
+        if spilled {
+            let slot = self.symbols.temp(self.temps);
+            self.temps += 1;
+            self.slots[v.0] = Some(slot);
+            self.code.push((Instruction::Store(slot), inst.span));
+        } else {
+            self.stack.push(v);
+            if self.remaining[v.0] == 0 {
+                self.stack.pop();
+                self.code.push((Instruction::Pop, inst.span));
+            }
+        }
+        Ok(())
+    }
+
+    /// Push again the spilled value `v`.
+    fn push(&mut self, v: ValueId, span: Span) -> Result<(), LowerError> {
+        let inst = match &self.line.inst(v).op {
+            Op::Const(c) => Instruction::PushValue(c.clone()),
+            _ => Instruction::Push(self.slots[v.0].ok_or(LowerError::Unscheduled(v))?),
+        };
+        self.code.push((inst, span));
+        Ok(())
+    }
+
+    /// Pop the `operands` of an instruction, in the order they were pushed,
+    /// duplicating those used again and pushing the spilled ones.
+    ///
+    /// Fails with the value to spill when the stack cannot give an operand.
+    fn take(&mut self, operands: &[ValueId], span: Span) -> Result<(), LowerError> {
+        match *operands {
+            [] => (),
+            [v] if self.spilled[v.0] => self.push(v, span)?,
+            [v] => {
+                if self.stack.last() != Some(&v) {
+                    return Err(LowerError::Unscheduled(v));
+                }
+                if self.remaining[v.0] > 1 {
+                    self.code.push((Instruction::Dup, span));
+                } else {
+                    self.stack.pop();
+                }
+                self.remaining[v.0] -= 1;
+            }
+            [a, b] if a == b && self.spilled[a.0] => {
+                self.push(a, span)?;
+                self.push(a, span)?;
+            }
+            [a, b] if a == b => {
+                if self.stack.last() != Some(&a) || self.remaining[a.0] < 2 {
+                    return Err(LowerError::Unscheduled(a));
+                }
+                self.code.push((Instruction::Dup, span));
+                if self.remaining[a.0] > 2 {
+                    self.code.push((Instruction::Dup, span));
+                } else {
+                    self.stack.pop();
+                }
+                self.remaining[a.0] -= 2;
+            }
+            [a, b] => match (self.spilled[a.0], self.spilled[b.0]) {
+                (true, true) => {
+                    self.push(a, span)?;
+                    self.push(b, span)?;
+                }
+                // `b` was pushed after `a`
+                (true, false) => return Err(LowerError::Unscheduled(b)),
+                (false, true) => {
+                    self.take(&[a], span)?;
+                    self.push(b, span)?;
+                }
+                (false, false) => {
+                    if self.stack.last() != Some(&b) || self.remaining[b.0] != 1 {
+                        return Err(LowerError::Unscheduled(b));
+                    }
+                    if !self.stack.ends_with(&[a, b]) || self.remaining[a.0] != 1 {
+                        return Err(LowerError::Unscheduled(a));
+                    }
+                    self.stack.truncate(self.stack.len() - 2);
+                    self.remaining[a.0] = 0;
+                    self.remaining[b.0] = 0;
+                }
+            },
+            _ => unreachable!("instructions have at most two operands"),
+        }
+        Ok(())
+    }
+}
+
+/// Build the IR of the statements of a line, allocating the slots of its
+/// variables in `symbols`.
+pub fn build(line: &[Spanned<Tree>], symbols: &mut SymbolTable) -> Result<IrLine, Spanned<String>> {
+    let mut builder = Builder {
+        ir: IrLine::default(),
+        current: BlockId(0),
+        symbols,
+    };
+    builder.new_block();
+    builder.statements(line)?;
+    Ok(builder.ir)
+}
+
+struct Builder<'a> {
+    ir: IrLine,
+    /// Block receiving the next instructions.
+    current: BlockId,
+    symbols: &'a mut SymbolTable,
+}
+
+impl Builder<'_> {
+    fn new_block(&mut self) -> BlockId {
+        self.ir.blocks.push(Block {
+            insts: vec![],
+            aborts: vec![],
+            exit: Exit::End,
+        });
+        BlockId(self.ir.blocks.len() - 1)
+    }
+
+    /// End the current block with `exit` and go on with `next`.
+    fn exit(&mut self, exit: Exit, next: BlockId) {
+        self.ir.blocks[self.current.0].exit = exit;
+        self.current = next;
+    }
+
+    fn push(&mut self, op: Op, span: Span) -> ValueId {
+        let ty = match op {
+            Op::Store(_, _) | Op::Discard(_) => None,
+            _ => {
+                let mut state = State {
+                    stack: op
+                        .operands()
+                        .iter()
+                        .map(|v| self.ir.inst(*v).ty.unwrap_or(Type::Unknown))
+                        .collect(),
+                    ram: vec![],
+                };
+                // an operation certain to fail never defines its value
+                step(&op.instruction(), &mut state);
+                Some(state.stack.last().copied().unwrap_or(Type::Unknown))
+            }
+        };
+        self.ir.insts.push(Inst { op, ty, span });
+        let value = ValueId(self.ir.insts.len() - 1);
+        let aborts = self.ir.may_abort(value);
+        let block = &mut self.ir.blocks[self.current.0];
+        block.insts.push(value);
+        if aborts {
+            block.aborts.push(value);
+        }
+        value
+    }
+
+    fn statements(&mut self, block: &[Spanned<Tree>]) -> Result<(), Spanned<String>> {
+        for s in block {
+            self.statement(s)?;
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: &Spanned<Tree>) -> Result<(), Spanned<String>> {
+        let span = token.span;
+        match &token.node {
+            Tree::Error(_) => {
+                // the statements after it never run
+                let next = self.new_block();
+                self.exit(Exit::Abort(span), next);
+            }
+            Tree::Comment(_) | Tree::Empty => (),
+            Tree::Assign(r, l) => {
+                let value = self.expr(l)?;
+                let adress = self.adress(r)?;
+                self.push(Op::Store(adress, value), span);
+            }
+            Tree::IfThen(p, t) => self.if_then_else(token, p, t, &[])?,
+            Tree::IfThenElse(p, t, f) => self.if_then_else(token, p, t, f)?,
+            Tree::Goto(t) => {
+                let target = self.expr(t)?;
+                // a string target is an error
+                if !matches!(self.ir.inst(target).ty, Some(Type::Int(_))) {
+                    self.ir.blocks[self.current.0].aborts.push(target);
+                }
+                let next = self.new_block();
+                self.exit(Exit::Goto(target, span), next);
+            }
+            _ => match update(&token.node) {
+                Some((op, r, l)) => {
+                    let adress = self.adress(r)?;
+                    let old = self.push(Op::Load(adress), r.span);
+                    let value = self.expr(l)?;
+                    let new = self.push(Op::Binary(op, old, value), span);
+                    self.push(Op::Store(adress, new), span);
+                }
+                None => {
+                    let value = self.expr(token)?;
+                    self.push(Op::Discard(value), span);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    fn if_then_else(
+        &mut self,
+        token: &Spanned<Tree>,
+        p: &Spanned<Tree>,
+        t: &[Spanned<Tree>],
+        f: &[Spanned<Tree>],
+    ) -> Result<(), Spanned<String>> {
+        let span = token.span;
+        let cond = self.expr(p)?;
+        let from = self.current;
+        let then = self.new_block();
+        self.current = then;
+        self.statements(t)?;
+        let otherwise = if f.is_empty() {
+            None
+        } else {
+            let then_end = self.current;
+            let otherwise = self.new_block();
+            self.current = otherwise;
+            self.statements(f)?;
+            Some((then_end, otherwise))
+        };
+        let join = self.new_block();
+        if let Some((then_end, _)) = otherwise {
+            self.ir.blocks[then_end.0].exit = Exit::Jump(join, span);
+        }
+        self.ir.blocks[from.0].exit = Exit::Branch {
+            cond,
+            then,
+            otherwise: otherwise.map_or(join, |(_, otherwise)| otherwise),
+            span,
+        };
+        self.exit(Exit::Jump(join, span), join);
+        Ok(())
+    }
+
+    /// Get the slot of the variable `token`, other trees cannot be assigned.
+    fn adress(&mut self, token: &Spanned<Tree>) -> Result<usize, Spanned<String>> {
+        match &token.node {
+            Tree::LocalVariable(v) => Ok(self.symbols.local(v)),
+            Tree::GlobalVariable(v) => Ok(self.symbols.global(v)),
+            _ => Err(Spanned::new(
+                "only a variable can be assigned".to_string(),
+                token.span,
+            )),
+        }
+    }
+
+    fn expr(&mut self, token: &Spanned<Tree>) -> Result<ValueId, Spanned<String>> {
+        let span = token.span;
+        if let Some((op, a, b)) = binary(&token.node) {
+            let a = self.expr(a)?;
+            let b = self.expr(b)?;
+            return Ok(self.push(Op::Binary(op, a, b), span));
+        }
+        if let Some((op, a)) = unary(&token.node) {
+            let a = self.expr(a)?;
+            return Ok(self.push(Op::Unary(op, a), span));
+        }
+        let value = match &token.node {
+            Tree::LocalVariable(_) | Tree::GlobalVariable(_) => {
+                let adress = self.adress(token)?;
+                self.push(Op::Load(adress), span)
+            }
+            Tree::Numerical(v) => self.push(Op::Const(YololInt::new_raw(*v).into()), span),
+            Tree::String(v) => self.push(Op::Const(v.as_str().into()), span),
+            Tree::PostInc(r) => self.step(token, r, UnOp::Inc, true)?,
+            Tree::PostDec(r) => self.step(token, r, UnOp::Dec, true)?,
+            Tree::PreInc(r) => self.step(token, r, UnOp::Inc, false)?,
+            Tree::PreDec(r) => self.step(token, r, UnOp::Dec, false)?,
+            _ => {
+                return Err(Spanned::new(
+                    "a statement cannot be used as a value".to_string(),
+                    span,
+                ))
+            }
+        };
+        Ok(value)
+    }
+
+    /// Increment or decrement a variable, giving the old value for a postfix
+    /// operator and the new one for a prefix operator.
+    fn step(
+        &mut self,
+        token: &Spanned<Tree>,
+        r: &Spanned<Tree>,
+        op: UnOp,
+        post: bool,
+    ) -> Result<ValueId, Spanned<String>> {
+        let adress = self.adress(r)?;
+        let old = self.push(Op::Load(adress), r.span);
+        let new = self.push(Op::Unary(op, old), token.span);
+        self.push(Op::Store(adress, new), token.span);
+        Ok(if post { old } else { new })
+    }
+}
+
+fn binary(tree: &Tree) -> Option<(BinOp, &Spanned<Tree>, &Spanned<Tree>)> {
+    let (op, a, b) = match tree {
+        Tree::Or(a, b) => (BinOp::Or, a, b),
+        Tree::And(a, b) => (BinOp::And, a, b),
+        Tree::Eq(a, b) => (BinOp::Eq, a, b),
+        Tree::Ne(a, b) => (BinOp::Ne, a, b),
+        Tree::Lt(a, b) => (BinOp::Lt, a, b),
+        Tree::Gt(a, b) => (BinOp::Gt, a, b),
+        Tree::Lte(a, b) => (BinOp::Lte, a, b),
+        Tree::Gte(a, b) => (BinOp::Gte, a, b),
+        Tree::Add(a, b) => (BinOp::Add, a, b),
+        Tree::Sub(a, b) => (BinOp::Sub, a, b),
+        Tree::Mul(a, b) => (BinOp::Mul, a, b),
+        Tree::Div(a, b) => (BinOp::Div, a, b),
+        Tree::Mod(a, b) => (BinOp::Mod, a, b),
+        Tree::Exp(a, b) => (BinOp::Exp, a, b),
+        _ => return None,
+    };
+    Some((op, a, b))
+}
+
+fn unary(tree: &Tree) -> Option<(UnOp, &Spanned<Tree>)> {
+    let (op, a) = match tree {
+        Tree::Abs(a) => (UnOp::Abs, a),
+        Tree::Sqrt(a) => (UnOp::Sqrt, a),
+        Tree::Sin(a) => (UnOp::Sin, a),
+        Tree::Cos(a) => (UnOp::Cos, a),
+        Tree::Tan(a) => (UnOp::Tan, a),
+        Tree::Asin(a) => (UnOp::Asin, a),
+        Tree::Acos(a) => (UnOp::Acos, a),
+        Tree::Atan(a) => (UnOp::Atan, a),
+        Tree::Not(a) => (UnOp::Not, a),
+        Tree::Fac(a) => (UnOp::Fac, a),
+        Tree::Neg(a) => (UnOp::Neg, a),
+        _ => return None,
+    };
+    Some((op, a))
+}
+
+/// Get the operator of an assignment like `a += b`, with its variable and
+/// value.
+fn update(tree: &Tree) -> Option<(BinOp, &Spanned<Tree>, &Spanned<Tree>)> {
+    let (op, r, l) = match tree {
+        Tree::AssignAdd(r, l) => (BinOp::Add, r, l),
+        Tree::AssignSub(r, l) => (BinOp::Sub, r, l),
+        Tree::AssignMul(r, l) => (BinOp::Mul, r, l),
+        Tree::AssignDiv(r, l) => (BinOp::Div, r, l),
+        Tree::AssignMod(r, l) => (BinOp::Mod, r, l),
+        Tree::AssignExp(r, l) => (BinOp::Exp, r, l),
+        _ => return None,
+    };
+    Some((op, r, l))
+}
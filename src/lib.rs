@@ -1,18 +1,72 @@
-mod ast;
+mod analysis;
+pub mod ast;
+mod chip;
+mod dataflow;
+mod diagnostic;
+mod fold;
+mod format;
+mod ir;
+mod limits;
+mod minify;
+mod opt;
 mod parser;
+mod passes;
+mod symbols;
+mod token;
+mod verify;
 mod vm;
 
 use std::fs::read_to_string;
 
-use ast::Tree;
-use lazy_static::__Deref;
+pub use analysis::Bool;
+pub use analysis::Type;
+pub use ast::Span;
+use ast::Spanned;
+pub use chip::ChipKind;
+pub use diagnostic::Diagnostic;
+pub use diagnostic::DiagnosticKind;
+pub use diagnostic::Severity;
+pub use format::format_line;
+pub use format::format_program;
+pub use format::format_tree;
+pub use format::Spacing;
+pub use ir::BinOp;
+pub use ir::Block;
+pub use ir::BlockId;
+pub use ir::Edge;
+pub use ir::Exit;
+pub use ir::Inst;
+pub use ir::IrLine;
+pub use ir::LowerError;
+pub use ir::Op;
+pub use ir::UnOp;
+pub use ir::ValueId;
+pub use limits::ChipLimits;
+pub use limits::LimitMode;
 use mimalloc::MiMalloc;
-use parser::yolol_parser;
-use vm::Instruction;
-use vm::VM;
+pub use minify::minify;
+pub use minify::Minified;
+pub use opt::check_levels;
+pub use opt::LevelMismatch;
+pub use opt::OptLevel;
+pub use opt::Passes;
+pub use parser::parse_program;
+pub use passes::LineReport;
+use passes::PassManager;
+pub use passes::PassReport;
+pub use symbols::SymbolTable;
+pub use token::tokenize;
+pub use token::Token;
+pub use token::TokenKind;
+pub use verify::verify;
+pub use verify::VerifyError;
+pub use verify::VerifyErrorKind;
+pub use vm::Instruction;
+pub use vm::RuntimeError;
+pub use vm::RuntimeErrorKind;
+pub use vm::VM;
 use yolol_devices::devices::chip::CodeRunner;
 use yolol_devices::field::Field;
-use yolol_devices::value::ValueTrait;
 use yolol_devices::value::YololValue;
 
 #[global_allocator]
@@ -20,780 +74,380 @@ static GLOBAL: MiMalloc = MiMalloc;
 
 #[derive(Debug, Default)]
 pub struct YololRunner {
-    lines: [Vec<Instruction>; 20],
-    pc: usize,
-    path: String,
+    path: Option<String>,
+    limits: Option<ChipLimits>,
+    chip: ChipKind,
+    passes: Passes,
     vm: VM,
-    variables: Vec<YololValue>,
-    stack: Vec<YololValue>,
+    symbols: SymbolTable,
+    diagnostics: Vec<Diagnostic>,
+    source_map: Vec<Vec<Span>>,
+    ir: Vec<IrLine>,
+    reports: Vec<LineReport>,
 }
 
 impl YololRunner {
-    fn process(&mut self, token: &Tree) -> Vec<Instruction> {
-        match token {
-            Tree::Error => vec![],
-            Tree::Comment(_) => vec![],
-            Tree::Assign(r, l) => self.process_assing(r, l),
-            Tree::IfThen(p, s) => {
-                let mut p = self.process_expr(p);
-                let mut s = s
-                    .iter()
-                    .map(|s| self.process(s))
-                    .reduce(|mut a, mut b| {
-                        a.append(&mut b);
-                        a
-                    })
-                    .unwrap_or_default();
-                p.push(Instruction::JumpFalse(s.len()));
-                p.append(&mut s);
-                p
-            }
-            Tree::IfThenElse(p, t, f) => {
-                let mut p = self.process_expr(p);
-                let mut t = t
-                    .iter()
-                    .map(|s| self.process(s))
-                    .reduce(|mut a, mut b| {
-                        a.append(&mut b);
-                        a
-                    })
-                    .unwrap_or_default();
-                let mut f = f
-                    .iter()
-                    .map(|s| self.process(s))
-                    .reduce(|mut a, mut b| {
-                        a.append(&mut b);
-                        a
-                    })
-                    .unwrap_or_default();
-                p.push(Instruction::JumpFalse(t.len() + 1));
-                p.append(&mut t);
-                p.push(Instruction::Jump(f.len()));
-                p.append(&mut f);
-                p
-            }
-            Tree::Goto(t) => {
-                let mut v = self.process_expr(t);
-                v.push(Instruction::Goto);
-                v
-            }
-            Tree::Empty => vec![],
-            t => self.process_expr(t),
+    /// Parse and compile a script held in memory.
+    pub fn from_source(source: &str) -> Option<Self> {
+        Self::builder().source(source).build()
+    }
+
+    pub fn builder() -> YololRunnerBuilder {
+        YololRunnerBuilder::default()
+    }
+
+    /// Parse the script at `path` and return every problem found in it.
+    ///
+    /// Lines with syntax errors run up to their first error, as the game does.
+    pub fn parse_with_diagnostics(&mut self, path: &str) -> Vec<Diagnostic> {
+        self.reset();
+        self.path = Some(path.to_string());
+        match read_to_string(path) {
+            Ok(file) => {
+                self.parse_source(&file);
+            }
+            Err(err) => self.diagnostics.push(Diagnostic::error(
+                self.path(),
+                0,
+                0,
+                format!("cannot read file : {}", err),
+            )),
         }
+        self.diagnostics.clone()
     }
 
-    fn process_assing(&mut self, r: &Tree, l: &Tree) -> Vec<Instruction> {
-        let mut value = self.process_expr(l);
+    /// Get the problems found while parsing the script.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
 
-        match r {
-            Tree::LocalVariable(v) | Tree::GlobalVariable(v) => value.push(Instruction::Store(*v)),
-            t => unreachable!("process_assing : {:?}", t),
-        };
-        value
-    }
-
-    fn process_expr(&mut self, token: &Tree) -> Vec<Instruction> {
-        match token {
-            Tree::LocalVariable(v) => vec![Instruction::Push(*v)],
-            Tree::GlobalVariable(v) => vec![Instruction::Push(*v)],
-            Tree::Numerical(v) => vec![Instruction::PushValue((*v as f64 / 1000.).into())],
-            Tree::String(v) => vec![Instruction::PushValue(v.as_str().into())],
-            Tree::Or(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Or);
-                b
-            }
-            Tree::And(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::And);
-                b
-            }
-            Tree::Eq(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Eq);
-                b
-            }
-            Tree::Ne(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Ne);
-                b
-            }
-            Tree::Gt(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Gt);
-                b
-            }
-            Tree::Lt(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Lt);
-                b
-            }
-            Tree::Gte(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Gte);
-                b
-            }
-            Tree::Lte(r, l) => {
-                let mut a = self.process_expr(r);
-                let mut b = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Lte);
-                b
-            }
-            Tree::Add(r, l) => {
-                let mut b = self.process_expr(r);
-                let mut a = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Add);
-                b
-            }
-            Tree::Sub(r, l) => {
-                let mut b = self.process_expr(r);
-                let mut a = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Sub);
-                b
-            }
-            Tree::Mul(r, l) => {
-                let mut b = self.process_expr(r);
-                let mut a = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Mul);
-                b
-            }
-            Tree::Div(r, l) => {
-                let mut b = self.process_expr(r);
-                let mut a = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Div);
-                b
-            }
-            Tree::Mod(r, l) => {
-                let mut b = self.process_expr(r);
-                let mut a = self.process_expr(l);
-                b.append(&mut a);
-                b.push(Instruction::Mod);
-                b
-            }
-            Tree::Neg(l) => {
-                let mut a = self.process_expr(l);
-                a.push(Instruction::PushValue(YololValue::from(-1)));
-                a.push(Instruction::Mul);
-                a
-            }
-            Tree::Fac(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Fac);
-                a
-            }
-            Tree::Abs(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Abs);
-                a
-            }
-            Tree::Sqrt(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Sqrt);
-                a
-            }
-            Tree::Sin(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Sin);
-                a
-            }
-            Tree::Asin(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Asin);
-                a
-            }
-            Tree::Cos(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Cos);
-                a
-            }
-            Tree::Acos(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Acos);
-                a
-            }
-            Tree::Tan(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Tan);
-                a
-            }
-            Tree::Atan(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Atan);
-                a
-            }
-            Tree::Not(r) => {
-                let mut a = self.process_expr(r);
-                a.push(Instruction::Not);
-                a
-            }
-            Tree::Exp(r, l) => {
-                let mut a = self.process_expr(l);
-                let mut b = self.process_expr(r);
-                b.append(&mut a);
-                b.push(Instruction::Exp);
-                b
-            }
+    /// Get the runtime error that aborted the line executed by the last step.
+    pub fn last_error(&self) -> Option<&RuntimeError> {
+        self.vm.last_error()
+    }
 
-            Tree::AssignAdd(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Add);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    /// Get the engine running the compiled script.
+    pub fn vm(&self) -> &VM {
+        &self.vm
+    }
 
-            Tree::AssignSub(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Sub);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    pub fn vm_mut(&mut self) -> &mut VM {
+        &mut self.vm
+    }
 
-            Tree::AssignMul(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Mul);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    /// Execute the current line, see [`VM::run`].
+    pub fn run(&mut self) -> Result<Option<YololValue>, RuntimeError> {
+        self.vm.run()
+    }
 
-            Tree::AssignDiv(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Div);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    /// Get the path or virtual file name of the script.
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
 
-            Tree::AssignMod(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Mod);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    /// Get the limits checked by the next parse, the ones of the chip unless
+    /// others were set.
+    pub fn limits(&self) -> ChipLimits {
+        self.limits.unwrap_or_else(|| self.chip.limits())
+    }
 
-            Tree::AssignExp(r, l) => {
-                let mut v = self.process_expr(l);
-                let addr = match **r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => v,
-                    _ => unreachable!(),
-                };
-                v.push(Instruction::Push(addr));
-                v.push(Instruction::Exp);
-                v.push(Instruction::Store(addr));
-                v
-            }
+    /// Set the limits checked by the next parse, in place of the ones of the
+    /// chip.
+    pub fn set_limits(&mut self, limits: ChipLimits) {
+        self.limits = Some(limits);
+    }
 
-            Tree::PostInc(r) => {
-                let addr = match &**r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => *v,
-                    _ => unreachable!(),
-                };
-                vec![
-                    Instruction::Push(addr),
-                    Instruction::Inc,
-                    Instruction::Dup,
-                    Instruction::Store(addr),
-                ]
-            }
-            Tree::PostDec(r) => {
-                let addr = match &**r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => *v,
-                    _ => unreachable!(),
-                };
-                vec![
-                    Instruction::Push(addr),
-                    Instruction::Dec,
-                    Instruction::Dup,
-                    Instruction::Store(addr),
-                ]
-            }
-            Tree::PreDec(r) => {
-                let addr = match &**r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => *v,
-                    _ => unreachable!(),
-                };
-                vec![
-                    Instruction::Push(addr),
-                    Instruction::Inc,
-                    Instruction::Dup,
-                    Instruction::Store(addr),
-                ]
-            }
-            Tree::PreInc(r) => {
-                let addr = match &**r {
-                    Tree::LocalVariable(v) | Tree::GlobalVariable(v) => *v,
-                    _ => unreachable!(),
-                };
-                vec![
-                    Instruction::Push(addr),
-                    Instruction::Dec,
-                    Instruction::Dup,
-                    Instruction::Store(addr),
-                ]
-            }
-            t => unreachable!("process_expr : {:?}", t),
-        }
+    pub fn chip(&self) -> ChipKind {
+        self.chip
     }
 
-    pub fn run(&mut self) -> Option<YololValue> {
-        self.stack.clear();
-        let mut pc = 0;
-        let instructions = &self.lines[self.pc];
-        while instructions.len() > pc as usize {
-            let instruction = &instructions[pc as usize];
-            //println!("{:?}", instruction);
-            match instruction {
-                Instruction::PushValue(value) => self.stack.push(value.clone()),
-                Instruction::Push(adress) => {
-                    let value = self.variables[*adress].clone();
-                    self.stack.push(value);
-                }
-                Instruction::Store(adress) => {
-                    self.variables[*adress] = self.stack.pop()?;
-                }
-                Instruction::Goto => return self.stack.pop(),
-                Instruction::Or => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = a.or(&b)
-                }
-                Instruction::And => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = a.and(&b)
-                }
-                Instruction::Eq => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a == b).into()
-                }
-                Instruction::Ne => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a != b).into()
-                }
-                Instruction::Lt => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a > b).into()
-                }
-                Instruction::Gt => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a < b).into()
-                }
-                Instruction::Lte => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a >= b).into()
-                }
-                Instruction::Gte => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (*a <= b).into()
-                }
-                Instruction::Add => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = &*a + &b
-                }
-                Instruction::Sub => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (&*a - &b)?
-                }
-                Instruction::Mul => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (&*a * &b)?
-                }
-                Instruction::Div => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (&*a / &b)?;
-                }
-                Instruction::Mod => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = (&*a % &b)?;
-                }
-                Instruction::Exp => {
-                    let b = self.stack.pop()?;
-                    let a = self.stack.last_mut()?;
-                    *a = a.pow(&b)?;
-                }
-                Instruction::Abs => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.abs()?;
-                }
+    /// Set the chip tier whose operators are accepted by the next parse.
+    pub fn set_chip(&mut self, chip: ChipKind) {
+        self.chip = chip;
+    }
 
-                Instruction::Sqrt => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.sqrt()?;
-                }
-                Instruction::Sin => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.sin()?;
-                }
-                Instruction::Cos => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.cos()?;
-                }
-                Instruction::Tan => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.tan()?;
-                }
-                Instruction::Asin => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.asin()?;
-                }
-                Instruction::Acos => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.acos()?;
-                }
-                Instruction::Atan => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.atan()?;
-                }
-                Instruction::Not => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.not();
-                }
-                Instruction::Fac => {
-                    let v = self.stack.last_mut()?;
-                    *v = v.fac()?;
-                }
-                Instruction::Inc => {
-                    let mut i = (self.stack.pop()?).clone();
-                    i.pre_inc();
-                    self.stack.push(i);
-                }
-                Instruction::Dec => {
-                    let mut i = (self.stack.pop()?).clone();
-                    i.pre_dec();
-                    self.stack.push(i);
-                }
-                Instruction::Jump(i) => pc = *i as isize,
-                Instruction::JumpFalse(i) => {
-                    let b: bool = (&self.stack.pop()?).into();
-                    if !b {
-                        pc += *i as isize;
-                    }
-                }
-                Instruction::Dup => {
-                    self.stack.push(self.stack.last()?.clone());
-                }
-                Instruction::Pop => {
-                    self.stack.pop();
+    pub fn passes(&self) -> Passes {
+        self.passes
+    }
+
+    /// Set the optimization passes run by the next parse.
+    pub fn set_passes(&mut self, passes: Passes) {
+        self.passes = passes;
+    }
+
+    /// Set the optimization passes run by the next parse to those of `level`.
+    pub fn set_opt_level(&mut self, level: OptLevel) {
+        self.passes = level.passes();
+    }
+
+    /// Forget the diagnostics and variables of an earlier parse.
+    fn reset(&mut self) {
+        self.diagnostics.clear();
+        self.symbols = SymbolTable::new();
+    }
+
+    fn parse_source(&mut self, source: &str) -> Option<()> {
+        self.parse_lines(&parser::split_lines(source))
+    }
+
+    fn parse_lines(&mut self, source: &[&str]) -> Option<()> {
+        let limits = self.limits();
+        let mut diagnostics = limits.check(self.path(), source);
+        self.diagnostics.append(&mut diagnostics);
+        let len = limits.program_len(source.len());
+
+        let mut ir: Vec<IrLine> = source
+            .iter()
+            .enumerate()
+            .map(|(i, s)| self.compile_line(i + 1, s))
+            .take(len)
+            .collect();
+        ir.resize(len, IrLine::default());
+        let lines: Vec<Code> = ir
+            .iter()
+            .enumerate()
+            .map(|(i, line)| self.lowered(i + 1, line).unwrap_or_default())
+            .collect();
+        self.ir = ir;
+
+        let globals: Vec<usize> = self.symbols.globals().map(|(_, a)| a).collect();
+        let (lines, reports) =
+            PassManager::new(self.passes).run(lines, source, self.symbols.len(), &globals);
+        for report in &reports {
+            for pass in &report.passes {
+                if let Some(err) = &pass.error {
+                    self.diagnostics.push(Diagnostic::error(
+                        self.path(),
+                        report.line,
+                        0,
+                        format!("internal compiler error in pass `{}`, {}", pass.name, err),
+                    ));
                 }
             }
-            pc += 1;
         }
-        None
-    }
-}
+        self.reports = reports;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Type {
-    String,
-    Int(Bool),
-    Unkown,
-}
+        let mut program = vec![vec![]; len];
+        self.source_map = vec![vec![]; len];
+        for (i, line) in lines.into_iter().enumerate() {
+            (program[i], self.source_map[i]) = line.into_iter().unzip();
+        }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum Bool {
-    True,
-    False,
-    Unknown,
-}
+        self.vm = VM::new(program, self.symbols.len());
+        Some(())
+    }
 
-fn optimize(mut insts: Vec<Instruction>, ram: &mut [Type]) -> Option<Vec<Instruction>> {
-    let mut error = true;
-    while error {
-        let mut stack: Vec<(usize, Type)> = Vec::new();
-        let mut i = 0;
-        let mut v = vec![];
-        let mut jump = 0;
-        error = false;
-        for (c, inst) in insts.iter().enumerate() {
-            if jump != 0 {
-                jump -= 1;
-                continue;
+    /// Lower the IR of the 1-indexed line `n` to checked bytecode, reporting
+    /// it if it cannot be.
+    fn lowered(&mut self, n: usize, line: &IrLine) -> Option<Code> {
+        match line.lower(&mut self.symbols) {
+            Ok(code) => self.verified(n, code),
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::error(
+                    self.path(),
+                    n,
+                    0,
+                    format!("internal compiler error, {}", err),
+                ));
+                None
             }
-            match &inst {
-                Instruction::Dup => {
-                    let v = stack.pop()?;
-                    stack.push((i, v.1));
-                    stack.push(v);
-                }
-                Instruction::Pop => {
-                    stack.pop()?;
-                }
-                Instruction::PushValue(v) => match v {
-                    YololValue::String(_) => stack.push((i, Type::String)),
-                    YololValue::Int(v) => {
-                        if v.into() {
-                            stack.push((i, Type::Int(Bool::True)))
-                        } else {
-                            stack.push((i, Type::Int(Bool::False)))
-                        }
-                    }
-                },
-                Instruction::Push(a) => stack.push((i, ram[*a])),
-                Instruction::Store(a) => ram[*a] = stack.pop()?.1,
-                Instruction::Goto => {
-                    if stack.pop()?.1 == Type::String {
-                        error = true;
-                        break;
-                    }
-                }
-                Instruction::Or => {
-                    let (_, a) = stack.pop()?;
-                    let (_, b) = stack.pop()?;
-                    if a == Type::Int(Bool::Unknown) || b == Type::Int(Bool::Unknown) {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    } else if (a == Type::String || a == Type::Int(Bool::False))
-                        && (b == Type::String || b == Type::Int(Bool::False))
-                    {
-                        stack.push((i, Type::Int(Bool::False)));
-                    } else {
-                        stack.push((i, Type::Int(Bool::True)));
-                    }
-                }
-                Instruction::And => {
-                    let (_, a) = stack.pop()?;
-                    let (_, b) = stack.pop()?;
-                    if a == Type::Int(Bool::Unknown) || b == Type::Int(Bool::Unknown) {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    } else if (a == Type::String || a == Type::Int(Bool::False))
-                        || (b == Type::String || b == Type::Int(Bool::False))
-                    {
-                        stack.push((i, Type::Int(Bool::False)));
-                    } else {
-                        stack.push((i, Type::Int(Bool::True)));
-                    }
-                }
-                Instruction::Eq
-                | Instruction::Ne
-                | Instruction::Lt
-                | Instruction::Gt
-                | Instruction::Lte
-                | Instruction::Gte => {
-                    stack.pop()?;
-                    stack.pop()?;
-                    stack.push((i, Type::Int(Bool::Unknown)));
-                }
-                Instruction::Add | Instruction::Sub => {
-                    let a = stack.pop()?;
-                    let b = stack.pop()?;
-                    if a.1 == Type::String || b.1 == Type::String {
-                        stack.push((i, Type::String));
-                    } else {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    }
-                }
-                Instruction::Mul | Instruction::Div | Instruction::Mod | Instruction::Exp => {
-                    let a = stack.pop()?;
-                    let b = stack.pop()?;
-                    if a.1 == Type::String || b.1 == Type::String {
-                        error = true;
-                        break;
-                    } else {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    }
-                }
-                Instruction::Abs
-                | Instruction::Sqrt
-                | Instruction::Sin
-                | Instruction::Cos
-                | Instruction::Tan
-                | Instruction::Asin
-                | Instruction::Acos
-                | Instruction::Atan
-                | Instruction::Fac => {
-                    if stack.pop()?.1 == Type::String {
-                        error = true;
-                        break;
-                    } else {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    }
-                }
-                Instruction::Not => {
-                    let (_, t) = stack.pop()?;
-                    if t == Type::String {
-                        stack.push((i, Type::String));
-                    } else if t == Type::Int(Bool::False) {
-                        stack.push((i, Type::Int(Bool::True)));
-                    } else if t == Type::Int(Bool::True) {
-                        stack.push((i, Type::Int(Bool::False)));
-                    } else {
-                        stack.push((i, Type::Int(Bool::Unknown)));
-                    }
-                }
+        }
+    }
 
-                Instruction::Inc | Instruction::Dec => {
-                    stack.pop()?;
-                    stack.push((i, Type::Int(Bool::Unknown)));
-                }
-                Instruction::JumpFalse(rel) => {
-                    let (_, t) = stack.pop()?;
-                    if t == Type::Int(Bool::True) || t == Type::Int(Bool::Unknown) {
-                        let mut ret = optimize(insts[c + 1..c + *rel + 1].to_vec(), ram)?;
-                        if t == Type::Int(Bool::Unknown) {
-                            i += 1;
-                            v.push(inst.clone());
-                        }
-
-                        i += ret.len();
-                        v.append(&mut ret);
-                    }
-                    jump = *rel;
-                    continue;
-                }
-                _ => (),
+    /// Check the bytecode of the 1-indexed line `n`, reporting it if invalid.
+    fn verified(&mut self, n: usize, line: Code) -> Option<Code> {
+        let instructions: Vec<Instruction> = line.iter().map(|(i, _)| i.clone()).collect();
+        match verify(&instructions, self.symbols.len()) {
+            Ok(()) => Some(line),
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::error(
+                    self.path(),
+                    n,
+                    0,
+                    format!("internal compiler error, {}", err),
+                ));
+                None
             }
-            v.push(inst.clone());
-            i += 1;
         }
-        if !stack.is_empty() {
-            error = true;
-            for (i, _) in stack.iter().rev() {
-                v.remove(*i);
+    }
+
+    /// Compile the 1-indexed line `n`, a line that cannot be compiled is
+    /// replaced by an empty line.
+    ///
+    /// Syntax errors are compiled to runtime errors, the statements before
+    /// them still run.
+    fn compile_line(&mut self, n: usize, source: &str) -> IrLine {
+        let (line, mut diagnostics) = parser::parse_line(self.path(), n, source);
+        self.diagnostics.append(&mut diagnostics);
+
+        let unsupported: Vec<Spanned<&str>> =
+            line.iter().flat_map(|s| self.chip.unsupported(s)).collect();
+        if !unsupported.is_empty() {
+            for operator in unsupported {
+                self.diagnostics.push(Diagnostic::error(
+                    self.path(),
+                    n,
+                    operator.span.column(source),
+                    format!(
+                        "operator `{}` is not available on {} chips",
+                        operator.node,
+                        self.chip.name()
+                    ),
+                ));
+            }
+            return IrLine::default();
+        }
+
+        match ir::build(&line, &mut self.symbols) {
+            Ok(line) => line,
+            Err(err) => {
+                self.diagnostics.push(Diagnostic::error(
+                    self.path(),
+                    n,
+                    err.span.column(source),
+                    err.node,
+                ));
+                IrLine::default()
             }
         }
-        insts = v;
     }
 
-    Some(insts)
+    /// Get the symbol table of the parsed script.
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Get the IR of every compiled line, before optimization.
+    pub fn ir(&self) -> &[IrLine] {
+        &self.ir
+    }
+
+    /// Get what every optimization pass did to each line, and why.
+    pub fn reports(&self) -> &[LineReport] {
+        &self.reports
+    }
+
+    /// Get the spans of the instructions of every compiled line, in the
+    /// order of [`VM::lines`].
+    pub fn source_map(&self) -> &[Vec<Span>] {
+        &self.source_map
+    }
+
+    /// Get the span of source that produced `instruction` of the 1-indexed
+    /// `line`, like the ones of a [`RuntimeError`].
+    pub fn span(&self, line: usize, instruction: usize) -> Option<Span> {
+        self.source_map
+            .get(line.checked_sub(1)?)?
+            .get(instruction)
+            .copied()
+    }
 }
 
-impl CodeRunner for YololRunner {
-    fn parse(&mut self, path: &str) -> Option<()> {
-        self.path = path.to_string();
-        if let Ok(file) = read_to_string(path) {
-            let mut lines: Vec<Vec<Instruction>> = file
-                .replace("\r\n", "\n")
-                .split('\n')
-                .map(|s| match yolol_parser::line(s) {
-                    Ok(line) => line
-                        .iter()
-                        .map(|s| self.process(s))
-                        .reduce(|mut a, mut b| {
-                            a.append(&mut b);
-                            a
-                        })
-                        .unwrap_or_default(),
-                    Err(err) => {
-                        println!("error {} line {}\n{}", self.path, self.pc + 1, err);
-                        vec![]
-                    }
-                })
-                .take(20)
-                .collect();
-            for (i, line) in lines.iter().enumerate() {
-                let mut ram = vec![];
-                for _ in 0..*crate::parser::I.lock() {
-                    ram.push(Type::Int(Bool::Unknown));
-                }
-                let line = optimize(line.clone(), &mut ram)?;
-                self.lines[i] = line;
-            }
-            lines = self.lines.to_vec();
+/// Build a [`YololRunner`] from lines of source held in memory.
+#[derive(Debug, Default, Clone)]
+pub struct YololRunnerBuilder {
+    name: Option<String>,
+    limits: Option<ChipLimits>,
+    chip: ChipKind,
+    passes: Passes,
+    lines: Vec<String>,
+}
 
-            for i in lines.len()..20 {
-                self.lines[i] = vec![];
-            }
+impl YololRunnerBuilder {
+    /// Set the virtual file name reported in diagnostics.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
 
-            for _ in 0..*crate::parser::I.lock() {
-                self.variables.push(YololValue::default());
-            }
+    /// Set the limits to check, in place of the ones of the chip.
+    pub fn limits(mut self, limits: ChipLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
 
-            self.stack = Vec::with_capacity(32);
-            return Some(());
-        }
-        None
+    pub fn chip(mut self, chip: ChipKind) -> Self {
+        self.chip = chip;
+        self
     }
 
-    fn step(&mut self) {
-        if self.pc == 20 {
-            self.pc = 0;
-        }
-        if self.lines[self.pc].is_empty() {
-            self.pc += 1;
-            return;
-        }
+    pub fn opt_level(mut self, level: OptLevel) -> Self {
+        self.passes = level.passes();
+        self
+    }
 
-        if let Some(YololValue::Int(v)) = self.run() {
-            let v: i64 = (&v).into();
-            self.pc = (v - 1).clamp(0, 19) as usize;
-        } else {
-            self.pc += 1;
-        }
+    pub fn passes(mut self, passes: Passes) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    pub fn line(mut self, line: &str) -> Self {
+        self.lines.push(line.to_string());
+        self
+    }
+
+    pub fn lines<I, S>(mut self, lines: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.lines
+            .extend(lines.into_iter().map(|s| s.as_ref().to_string()));
+        self
+    }
+
+    /// Append every line of `source`.
+    pub fn source(self, source: &str) -> Self {
+        self.lines(parser::split_lines(source))
+    }
+
+    pub fn build(self) -> Option<YololRunner> {
+        let mut runner = YololRunner {
+            path: self.name,
+            limits: self.limits,
+            chip: self.chip,
+            passes: self.passes,
+            ..YololRunner::default()
+        };
+        let lines: Vec<&str> = self
+            .lines
+            .iter()
+            .flat_map(|l| parser::split_lines(l))
+            .collect();
+        runner.parse_lines(&lines)?;
+        Some(runner)
+    }
+}
+
+/// Compiled instructions with the span of source each comes from.
+type Code = Vec<(Instruction, Span)>;
+
+impl CodeRunner for YololRunner {
+    fn parse(&mut self, path: &str) -> Option<()> {
+        self.reset();
+        self.path = Some(path.to_string());
+        let file = read_to_string(path).ok()?;
+        self.parse_source(&file)
+    }
+
+    fn step(&mut self) {
+        self.vm.step()
     }
 
     fn update_globals(&mut self, globals: Vec<Field>) {
-        let mut vec = vec![];
         for global in globals {
-            let k = global.name().to_lowercase();
-            let adress = crate::parser::GLOBALS.lock()[&k];
-
-            while vec.len() <= adress {
-                vec.push(YololValue::default())
+            if let Some(adress) = self.symbols.get_global(global.name()) {
+                self.vm.set_variable(adress, (*global).clone());
             }
-
-            vec[adress] = (*global).clone();
         }
-        self.variables = vec;
     }
 
     fn get_global(&self) -> Vec<Field> {
         let mut v = vec![];
-        for (name, adress) in crate::parser::GLOBALS.lock().deref() {
+        for (name, adress) in self.symbols.globals() {
             let mut global = Field::default();
-            global.set_name(name.clone());
-            if let Some(value) = self.variables.get(*adress) {
+            global.set_name(name.to_string());
+            if let Some(value) = self.vm.variable(adress) {
                 *global = (*value).clone();
             }
             v.push(global);
@@ -0,0 +1,110 @@
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::DiagnosticKind;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitMode {
+    /// Lines past the limit are dropped and long lines are accepted, both
+    /// reported as warnings.
+    Lenient,
+    /// Scripts over the limits are reported as errors, like the game rejects them.
+    Strict,
+    /// No limit at all, for development.
+    Unlimited,
+}
+
+/// Size limits of the chip a script runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChipLimits {
+    pub max_lines: usize,
+    pub max_line_length: usize,
+    pub mode: LimitMode,
+}
+
+impl ChipLimits {
+    /// The 20 lines of 70 characters of in-game chips.
+    pub const GAME: Self = Self {
+        max_lines: 20,
+        max_line_length: 70,
+        mode: LimitMode::Lenient,
+    };
+
+    /// Limits of the basic YOLOL chip.
+    pub const BASIC: Self = Self::GAME;
+
+    /// Limits of the advanced YOLOL chip.
+    pub const ADVANCED: Self = Self::GAME;
+
+    /// Limits of the professional YOLOL chip.
+    pub const PROFESSIONAL: Self = Self::GAME;
+
+    pub fn strict(self) -> Self {
+        Self {
+            mode: LimitMode::Strict,
+            ..self
+        }
+    }
+
+    pub fn unlimited(self) -> Self {
+        Self {
+            mode: LimitMode::Unlimited,
+            ..self
+        }
+    }
+
+    /// Number of lines of the program for a script of `len` lines.
+    pub fn program_len(&self, len: usize) -> usize {
+        match self.mode {
+            LimitMode::Unlimited => len,
+            _ => self.max_lines,
+        }
+    }
+
+    /// Report the lines of `lines` that do not fit the chip.
+    pub fn check(&self, file: Option<&str>, lines: &[&str]) -> Vec<Diagnostic> {
+        let report = match self.mode {
+            LimitMode::Lenient => Diagnostic::warning,
+            LimitMode::Strict => Diagnostic::error,
+            LimitMode::Unlimited => return vec![],
+        };
+        let mut diagnostics = vec![];
+        for (i, line) in lines.iter().enumerate().take(self.max_lines) {
+            let len = line.chars().count();
+            if len > self.max_line_length {
+                diagnostics.push(
+                    report(
+                        file,
+                        i + 1,
+                        self.max_line_length + 1,
+                        format!(
+                            "line is {} characters long, chip allows {}",
+                            len, self.max_line_length
+                        ),
+                    )
+                    .with_kind(DiagnosticKind::LineLength),
+                );
+            }
+        }
+        let len = lines
+            .iter()
+            .rposition(|l| !l.trim().is_empty())
+            .map_or(0, |i| i + 1);
+        if len > self.max_lines {
+            diagnostics.push(
+                report(
+                    file,
+                    self.max_lines + 1,
+                    0,
+                    format!("script has {} lines, chip allows {}", len, self.max_lines),
+                )
+                .with_kind(DiagnosticKind::ScriptLength),
+            );
+        }
+        diagnostics
+    }
+}
+
+impl Default for ChipLimits {
+    fn default() -> Self {
+        Self::GAME
+    }
+}
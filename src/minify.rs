@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+
+use crate::ast::fold_line;
+use crate::ast::fold_tree;
+use crate::ast::walk_tree;
+use crate::ast::Fold;
+use crate::ast::Spanned;
+use crate::ast::Tree;
+use crate::ast::Visitor;
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::DiagnosticKind;
+use crate::format::format_line;
+use crate::format::Spacing;
+use crate::limits::ChipLimits;
+use crate::parser::parse_program;
+use crate::parser::split_lines;
+use crate::parser::KEYWORDS;
+
+/// Result of [`minify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Minified {
+    pub source: String,
+    /// Lines that failed to parse, kept as they were, and lines still longer
+    /// than a chip allows.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Minified {
+    /// Get the 1-indexed lines still longer than a chip allows.
+    pub fn long_lines(&self) -> Vec<usize> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.kind == DiagnosticKind::LineLength)
+            .map(|d| d.line)
+            .collect()
+    }
+}
+
+/// Shorten a script without changing what it does.
+///
+/// Comments, spaces and parentheses the parser does not need are removed,
+/// locals are renamed to the shortest free names, the most used first, and
+/// numbers lose their useless zeros. Globals are never renamed as other
+/// devices read them.
+pub fn minify(source: &str) -> Minified {
+    let program = parse_program(source);
+
+    let source = split_lines(source);
+
+    // broken lines are kept as they are, so are the names they might use
+    let mut kept: Vec<String> = vec![];
+    for (original, line) in source.iter().zip(&program.lines) {
+        if line.iter().any(|s| matches!(s.node, Tree::Error(_))) {
+            kept.extend(
+                original
+                    .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .map(|w| w.to_lowercase()),
+            );
+        }
+    }
+
+    let mut counter = Locals::default();
+    for line in &program.lines {
+        counter.visit_line(line);
+    }
+    let mut locals: Vec<(String, (usize, usize))> = counter
+        .0
+        .into_iter()
+        .filter(|(name, _)| !kept.contains(name))
+        .collect();
+    // most used first, then in order of appearance
+    locals.sort_by_key(|(_, (count, first))| (std::cmp::Reverse(*count), *first));
+    let mut rename = Rename(
+        locals
+            .into_iter()
+            .map(|(name, _)| name)
+            .zip(names().filter(|name| !kept.contains(name)))
+            .collect(),
+    );
+
+    let lines: Vec<String> = source
+        .iter()
+        .zip(program.lines)
+        .map(|(original, line)| {
+            if line.iter().any(|s| matches!(s.node, Tree::Error(_))) {
+                original.to_string()
+            } else {
+                format_line(&rename.fold_line(line), Spacing::Compact)
+            }
+        })
+        .collect();
+
+    let mut diagnostics = program.diagnostics;
+    let lines_ref: Vec<&str> = lines.iter().map(|l| l.as_str()).collect();
+    diagnostics.append(&mut ChipLimits::GAME.check(None, &lines_ref));
+    Minified {
+        source: lines.join("\n"),
+        diagnostics,
+    }
+}
+
+/// Get the valid local names, shortest first.
+fn names() -> impl Iterator<Item = String> {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    (1..)
+        .flat_map(|len: u32| {
+            (0..FIRST.len() * REST.len().pow(len - 1)).map(move |mut i| {
+                let mut name = vec![FIRST[i % FIRST.len()]];
+                i /= FIRST.len();
+                for _ in 1..len {
+                    name.push(REST[i % REST.len()]);
+                    i /= REST.len();
+                }
+                String::from_utf8(name).unwrap_or_default()
+            })
+        })
+        .filter(|name| !KEYWORDS.contains(&name.as_str()))
+}
+
+/// Count the uses of every local and the order they first appear in.
+#[derive(Default)]
+struct Locals(BTreeMap<String, (usize, usize)>);
+
+impl Visitor for Locals {
+    fn visit_tree(&mut self, tree: &Spanned<Tree>) {
+        if let Tree::LocalVariable(name) = &tree.node {
+            let next = self.0.len();
+            self.0.entry(name.clone()).or_insert((0, next)).0 += 1;
+        }
+        walk_tree(self, tree)
+    }
+}
+
+struct Rename(BTreeMap<String, String>);
+
+impl Fold for Rename {
+    fn fold_line(&mut self, line: Vec<Spanned<Tree>>) -> Vec<Spanned<Tree>> {
+        let line = line
+            .into_iter()
+            .filter(|s| !matches!(s.node, Tree::Comment(_)))
+            .collect();
+        fold_line(self, line)
+    }
+
+    fn fold_tree(&mut self, tree: Spanned<Tree>) -> Spanned<Tree> {
+        match tree.node {
+            Tree::LocalVariable(name) => {
+                let name = self.0.get(&name).cloned().unwrap_or(name);
+                Spanned::new(Tree::LocalVariable(name), tree.span)
+            }
+            _ => fold_tree(self, tree),
+        }
+    }
+}
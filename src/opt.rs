@@ -0,0 +1,198 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_devices::value::YololValue;
+
+use crate::ast::Span;
+use crate::vm::RuntimeError;
+use crate::vm::RuntimeErrorKind;
+use crate::YololRunner;
+use crate::YololRunnerBuilder;
+
+/// How much the compiled lines are optimized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// Lines run exactly as they are written, for debugging.
+    None,
+    /// Each line is optimized on its own.
+    Basic,
+    /// Lines are optimized with what the lines running before them tell.
+    #[default]
+    Aggressive,
+}
+
+impl OptLevel {
+    pub const ALL: [OptLevel; 3] = [OptLevel::None, OptLevel::Basic, OptLevel::Aggressive];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptLevel::None => "none",
+            OptLevel::Basic => "basic",
+            OptLevel::Aggressive => "aggressive",
+        }
+    }
+
+    /// Get the passes run at this level.
+    pub fn passes(&self) -> Passes {
+        match self {
+            OptLevel::None => Passes {
+                fold: false,
+                dead_branches: false,
+                failing_tail: false,
+                stack_cleanup: false,
+                dataflow: false,
+            },
+            OptLevel::Basic => Passes {
+                dataflow: false,
+                ..OptLevel::Aggressive.passes()
+            },
+            OptLevel::Aggressive => Passes {
+                fold: true,
+                dead_branches: true,
+                failing_tail: true,
+                stack_cleanup: true,
+                dataflow: true,
+            },
+        }
+    }
+}
+
+/// The optimization passes run on every compiled line, in the order of the
+/// fields, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Passes {
+    /// `fold`: compute the operations on constants once.
+    pub fold: bool,
+    /// `dead-branches`: drop the code that never runs, like the branches of an
+    /// `if` whose condition is known.
+    pub dead_branches: bool,
+    /// `failing-tail`: end a line after an operation certain to fail on a
+    /// string.
+    pub failing_tail: bool,
+    /// `stack-cleanup`: drop the values pushed only to be popped.
+    pub stack_cleanup: bool,
+    /// Give the passes using types those known from the lines running before,
+    /// instead of knowing nothing when a line starts.
+    pub dataflow: bool,
+}
+
+impl Default for Passes {
+    fn default() -> Self {
+        OptLevel::default().passes()
+    }
+}
+
+impl From<OptLevel> for Passes {
+    fn from(level: OptLevel) -> Self {
+        level.passes()
+    }
+}
+
+/// Globals or runtime error of a run differing between two optimization
+/// levels, found by [`check_levels`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelMismatch {
+    pub level: OptLevel,
+    /// 1-indexed step after which the runs differ.
+    pub step: usize,
+    /// Globals without optimization.
+    pub expected: Vec<(String, YololValue)>,
+    pub found: Vec<(String, YololValue)>,
+    /// Runtime error of the step without optimization.
+    pub expected_error: Option<RuntimeError>,
+    pub found_error: Option<RuntimeError>,
+}
+
+impl Display for LevelMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.expected == self.found {
+            let error = |e: &Option<RuntimeError>| match e {
+                Some(e) => format!("line {} : {}", e.line, e.kind),
+                None => "none".to_string(),
+            };
+            return write!(
+                f,
+                "runtime errors differ after step {} at level {} : expected {}, found {}",
+                self.step,
+                self.level.name(),
+                error(&self.expected_error),
+                error(&self.found_error)
+            );
+        }
+        write!(
+            f,
+            "globals differ after step {} at level {} : expected {:?}, found {:?}",
+            self.step,
+            self.level.name(),
+            self.expected,
+            self.found
+        )
+    }
+}
+
+impl Error for LevelMismatch {}
+
+/// Globals and runtime error after a step of [`check_levels`].
+struct Snapshot {
+    globals: Vec<(String, YololValue)>,
+    error: Option<RuntimeError>,
+    /// Line, kind and span of source of the error, the index of the
+    /// instruction changes with the optimizations.
+    site: Option<(usize, RuntimeErrorKind, Option<Span>)>,
+}
+
+impl Snapshot {
+    fn new(runner: &YololRunner) -> Self {
+        let error = runner.last_error().cloned();
+        Self {
+            globals: runner
+                .get_global()
+                .iter()
+                .map(|g| (g.name().to_string(), (**g).clone()))
+                .collect(),
+            site: error
+                .as_ref()
+                .map(|e| (e.line, e.kind, runner.span(e.line, e.instruction))),
+            error,
+        }
+    }
+}
+
+/// Run the script of `builder` for `steps` steps at every [`OptLevel`],
+/// checking that the globals and the runtime error are the same after every
+/// step.
+pub fn check_levels(builder: &YololRunnerBuilder, steps: usize) -> Result<(), LevelMismatch> {
+    let traces: Vec<Vec<Snapshot>> = OptLevel::ALL
+        .iter()
+        .map(|level| {
+            let mut runner = builder
+                .clone()
+                .opt_level(*level)
+                .build()
+                .unwrap_or_default();
+            (0..steps)
+                .map(|_| {
+                    runner.step();
+                    Snapshot::new(&runner)
+                })
+                .collect()
+        })
+        .collect();
+
+    for (level, trace) in OptLevel::ALL.iter().zip(&traces).skip(1) {
+        for (step, (expected, found)) in traces[0].iter().zip(trace).enumerate() {
+            if expected.globals != found.globals || expected.site != found.site {
+                return Err(LevelMismatch {
+                    level: *level,
+                    step: step + 1,
+                    expected: expected.globals.clone(),
+                    found: found.globals.clone(),
+                    expected_error: expected.error.clone(),
+                    found_error: found.error.clone(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,38 +1,272 @@
 #![allow(clippy::all)]
 
-use std::collections::BTreeMap;
+use crate::ast::Program;
+use crate::ast::Span;
+use crate::ast::Spanned;
+use crate::ast::Tree;
+use crate::diagnostic::Diagnostic;
+use crate::diagnostic::DiagnosticKind;
 
-use lazy_static::lazy_static;
-use parking_lot::Mutex;
+type Node = Box<Spanned<Tree>>;
 
-use crate::ast::Tree;
+/// Build the node of a binary operator spanning both operands.
+fn binary(l: Spanned<Tree>, r: Spanned<Tree>, tree: fn(Node, Node) -> Tree) -> Spanned<Tree> {
+    let span = Span::new(l.span.start, r.span.end);
+    Spanned::new(tree(l.into(), r.into()), span)
+}
+
+/// Build the node of a prefix operator starting at `start`.
+fn unary(start: usize, r: Spanned<Tree>, tree: fn(Node) -> Tree) -> Spanned<Tree> {
+    let span = Span::new(start, r.span.end);
+    Spanned::new(tree(r.into()), span)
+}
 
-lazy_static! {
-    pub static ref GLOBALS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
-    pub static ref LOCALS: Mutex<BTreeMap<String, usize>> = Mutex::new(BTreeMap::new());
-    pub static ref I: Mutex<usize> = Mutex::new(0);
+/// Build the node of a postfix operator ending at `end`.
+fn postfix(l: Spanned<Tree>, end: usize, tree: fn(Node) -> Tree) -> Spanned<Tree> {
+    let span = Span::new(l.span.start, end);
+    Spanned::new(tree(l.into()), span)
 }
 
-fn get_local(key: &str) -> usize {
-    let key = &key.to_lowercase();
-    if LOCALS.lock().contains_key(key) {
-        LOCALS.lock()[key]
+/// Convert the digits of a literal to thousandths, decimals past the third
+/// are truncated and values out of range saturate.
+fn fixed(negative: bool, int: &str, frac: &str) -> i64 {
+    let int = int.trim_start_matches('0');
+    let frac: String = frac.chars().chain("000".chars()).take(3).collect();
+    // 20 digits always overflow once multiplied by 1000
+    let v = if int.len() >= 20 {
+        i128::MAX
     } else {
-        LOCALS.lock().insert(key.to_string(), *I.lock());
-        *I.lock() += 1;
-        *I.lock() - 1
+        int.parse::<i128>().unwrap_or(0) * 1000 + frac.parse::<i128>().unwrap_or(0)
+    };
+    let v = if negative { -v } else { v };
+    v.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Words that cannot name a variable.
+pub const KEYWORDS: [&str; 16] = [
+    "if", "then", "else", "end", "goto", "and", "or", "not", "abs", "sqrt", "sin", "cos", "tan",
+    "asin", "acos", "atan",
+];
+
+/// Deepest nesting accepted by [`nesting`], the parser recurses once per level
+/// and would overflow the stack on deeper lines.
+pub const MAX_NESTING: usize = 100;
+
+/// Get an upper bound of how deep the parser recurses on `line`.
+///
+/// Every parenthesis, `if` and prefix operator opens a level, prefix operators
+/// are only closed with their parenthesis. A `-` after an operand is a binary
+/// one and opens nothing.
+pub fn nesting(line: &str) -> usize {
+    const PREFIX: [&str; 10] = [
+        "if", "not", "abs", "sqrt", "sin", "cos", "tan", "asin", "acos", "atan",
+    ];
+    // prefix operators opened in each enclosing parenthesis
+    let mut outer: Vec<usize> = vec![];
+    let mut open = 0;
+    let mut max = 0;
+    // the last token ends an operand, so a `-` is binary
+    let mut operand = false;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => {
+                while chars.next().map_or(false, |(_, c)| c != '"') {}
+                operand = true;
+            }
+            '/' if line[i..].starts_with("//") => break,
+            '(' => {
+                outer.push(std::mem::take(&mut open));
+                operand = false;
+            }
+            ')' => {
+                open = outer.pop().unwrap_or(open);
+                operand = true;
+            }
+            '-' if operand => operand = false,
+            '-' => open += 1,
+            '!' => operand = true,
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let mut end = i + 1;
+                while let Some(&(j, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                let word = &line[i..end];
+                if PREFIX.iter().any(|k| word.eq_ignore_ascii_case(k)) {
+                    open += 1;
+                }
+                operand = !KEYWORDS.iter().any(|k| word.eq_ignore_ascii_case(k));
+            }
+            c if c.is_whitespace() || c == ':' => (),
+            _ => operand = false,
+        }
+        max = max.max(outer.iter().sum::<usize>() + outer.len() + open);
+    }
+    max
+}
+
+/// Most operators accepted by [`operators`], a chain of binary operators is
+/// as deep as it is long and the passes walk it recursively.
+pub const MAX_OPERATORS: usize = 500;
+
+/// Get an upper bound of how many operators `line` holds.
+///
+/// Every symbol outside of strings and comments counts, and so do the words
+/// that are operators.
+pub fn operators(line: &str) -> usize {
+    const WORDS: [&str; 11] = [
+        "and", "or", "not", "abs", "sqrt", "sin", "cos", "tan", "asin", "acos", "atan",
+    ];
+    let mut count = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => while chars.next().map_or(false, |(_, c)| c != '"') {},
+            '/' if line[i..].starts_with("//") => break,
+            c if c.is_ascii_alphanumeric() || c == '_' || c == '.' => {
+                let mut end = i + 1;
+                while let Some(&(j, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                let word = &line[i..end];
+                if WORDS.iter().any(|k| word.eq_ignore_ascii_case(k)) {
+                    count += 1;
+                }
+            }
+            c if c.is_whitespace() || c == ':' || c == '(' || c == ')' => (),
+            _ => count += 1,
+        }
     }
+    count
 }
 
-fn get_global(key: &str) -> usize {
-    let key = &key.to_lowercase();
-    if GLOBALS.lock().contains_key(key) {
-        GLOBALS.lock()[key]
+/// Parse the 1-indexed line `n` of `file` and report its syntax errors.
+///
+/// The parser recovers at the next statement start after an error, so the
+/// statements around a broken segment are kept and the segment becomes a
+/// [`Tree::Error`]. A broken `if` is skipped up to its `end`.
+pub fn parse_line(
+    file: Option<&str>,
+    n: usize,
+    line: &str,
+) -> (Vec<Spanned<Tree>>, Vec<Diagnostic>) {
+    let message = if nesting(line) > MAX_NESTING {
+        Some(format!(
+            "line is nested more than {} levels deep",
+            MAX_NESTING
+        ))
+    } else if operators(line) > MAX_OPERATORS {
+        Some(format!("line has more than {} operators", MAX_OPERATORS))
     } else {
-        GLOBALS.lock().insert(key.to_string(), *I.lock());
-        *I.lock() += 1;
-        *I.lock() - 1
+        None
+    };
+    if let Some(message) = message {
+        let error = Diagnostic::error(file, n, 0, message).with_kind(DiagnosticKind::Syntax);
+        let span = Span::new(0, line.len());
+        return (
+            vec![Spanned::new(Tree::Error(line.to_string()), span)],
+            vec![error],
+        );
     }
+    let err = match yolol_parser::line(line) {
+        Ok(stmts) => return (stmts, vec![]),
+        Err(err) => err,
+    };
+    let stmts = yolol_parser::recovering_line(line)
+        .map(|stmts| merge_errors(line, stmts))
+        .unwrap_or_else(|_| {
+            let error = Tree::Error(line.to_string());
+            vec![Spanned::new(error, Span::new(0, line.len()))]
+        });
+    let mut diagnostics = vec![];
+    for error in stmts.iter().filter(|s| matches!(s.node, Tree::Error(_))) {
+        let column = error.span.column(line);
+        // the error the parser stopped at knows what it expected
+        let diagnostic = if (error.span.start..error.span.end).contains(&err.location.offset) {
+            Diagnostic {
+                column,
+                ..Diagnostic::from_parse_error(file, n, &err)
+            }
+        } else {
+            Diagnostic::error(
+                file,
+                n,
+                column,
+                format!("cannot parse `{}`", &line[error.span.start..error.span.end]),
+            )
+            .with_kind(DiagnosticKind::Syntax)
+        };
+        diagnostics.push(diagnostic);
+    }
+    if diagnostics.is_empty() {
+        diagnostics.push(Diagnostic::from_parse_error(file, n, &err));
+    }
+    (stmts, diagnostics)
+}
+
+/// Merge the statements an error directly follows, as they are the broken
+/// part.
+fn merge_errors(line: &str, stmts: Vec<Spanned<Tree>>) -> Vec<Spanned<Tree>> {
+    let mut merged: Vec<Spanned<Tree>> = vec![];
+    for stmt in stmts {
+        merged.push(stmt);
+        while let [.., a, b] = &merged[..] {
+            if !matches!(b.node, Tree::Error(_)) || a.span.end != b.span.start {
+                break;
+            }
+            let end = b.span.end;
+            merged.pop();
+            if let Some(a) = merged.last_mut() {
+                a.span.end = end;
+                a.node = Tree::Error(line[a.span.start..end].to_string());
+            }
+        }
+    }
+    merged
+}
+
+/// Split a script in lines.
+///
+/// Lines end with `\n`, `\r\n` or a lone `\r`, a line break at the end of
+/// the script does not start another line and a leading byte order mark is
+/// ignored.
+pub fn split_lines(source: &str) -> Vec<&str> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut chars = source.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\n' || c == '\r' {
+            lines.push(&source[start..i]);
+            if c == '\r' && chars.peek().map(|(_, c)| *c) == Some('\n') {
+                chars.next();
+            }
+            start = chars.peek().map_or(source.len(), |(j, _)| *j);
+        }
+    }
+    if start < source.len() || lines.is_empty() {
+        lines.push(&source[start..]);
+    }
+    lines
+}
+
+/// Parse every line of a script without compiling it.
+pub fn parse_program(source: &str) -> Program {
+    let mut program = Program::default();
+    for (i, line) in split_lines(source).into_iter().enumerate() {
+        let (line, mut diagnostics) = parse_line(None, i + 1, line);
+        program.lines.push(line);
+        program.diagnostics.append(&mut diagnostics);
+    }
+    program
 }
 
 peg::parser! {
@@ -40,7 +274,10 @@ peg::parser! {
         #[cache]
         rule nl() = "\n" / "\r\n"
         #[cache]
-        rule ss() = (" ")*
+        // spaces and tabs are the only whitespace inside a line
+        rule ws() = [' ' | '\t']
+        #[cache]
+        rule ss() = ws()*
 
         #[cache]
         rule alpha() -> String = n:$(['a'..='z'] / ['A'..='Z'] / ['_']) { n.to_string() }
@@ -49,77 +286,90 @@ peg::parser! {
         #[cache]
         rule alphanumeric() -> String = digit() / alpha()
 
-        pub rule line() -> Vec<Tree> = s:(" "* s:stmt() {s})* " "* {s} //ls:( s:stmt() {s})* [_] {let mut s = vec![s]; s.append(&mut ls.clone());s}
-        rule stmt() -> Tree = goto() / if_then_end() / (a:assignment() {a}) / comment() / expression()  // "" {Tree::Empty}
-        rule goto() -> Tree = "goto" ss() e:expression() {Tree::Goto(e.into())}
-        rule if_then_end() -> Tree = "if" ss() p:expression() ss() "then" l:line() ss() e:("else" l:line() ss() {l})? "end" {
+        // case insensitive keyword, not followed by an identifier character
+        rule kw(k: &'static str) = quiet!{(s:$([_]*<{k.len()}>) {? if s.eq_ignore_ascii_case(k) {Ok(())} else {Err(k)}}) !alphanumeric()} / {? Err(k)}
+        rule keyword() = kw("if") / kw("then") / kw("else") / kw("end") / kw("goto") / kw("and") / kw("or") / kw("not")
+            / kw("abs") / kw("sqrt") / kw("sin") / kw("cos") / kw("tan") / kw("asin") / kw("acos") / kw("atan")
+
+        pub rule line() -> Vec<Spanned<Tree>> = s:(ss() s:stmt() {s})* ss() {s} //ls:( s:stmt() {s})* [_] {let mut s = vec![s]; s.append(&mut ls.clone());s}
+        // like line, a segment that is not a statement is an error up to the next statement start
+        pub rule recovering_line() -> Vec<Spanned<Tree>> = s:(ss() s:(stmt() / error()) {s})* ss() {s}
+        rule error() -> Spanned<Tree> = spanned(<e:$((variable() / skipped()) (!(ss() (start() / ![_])) skipped())*) {Tree::Error(e.to_string())}>)
+        rule start() = variable() ss() (("=" !"=") / "+=" / "-=" / "*=" / "/=" / "%=" / "^=" / "++" / "--") / kw("if") / kw("goto")
+        rule skipped() = "\"" [^ '"']* "\""? / broken_if() / alpha() alphanumeric()* / digit()+ / [_]
+        // an if is skipped along with its body, up to its end
+        rule broken_if() = ['i' | 'I'] ['f' | 'F'] !alphanumeric() (!kw("end") skipped())* kw("end")?
+        rule stmt() -> Spanned<Tree> = goto() / if_then_end() / (a:assignment() {a}) / comment() / expression()  // "" {Tree::Empty}
+        rule goto() -> Spanned<Tree> = s:position!() kw("goto") ss() e:expression() {unary(s, e, Tree::Goto)}
+        rule if_then_end() -> Spanned<Tree> = s:position!() kw("if") ss() p:expression() ss() kw("then") l:line() ss() e:(kw("else") l:line() ss() {l})? kw("end") end:position!() {
+            let span = Span::new(s, end);
             if let Some(e) = e {
-                Tree::IfThenElse(p.into(), l, e)
+                Spanned::new(Tree::IfThenElse(p.into(), l, e), span)
             } else {
-                Tree::IfThen(p.into(), l)
+                Spanned::new(Tree::IfThen(p.into(), l), span)
             }
         }
-        rule assignment() -> Tree =
-            l:variable() ss() "=" ss() r:expression() {Tree::Assign(l.into(), r.into())}
-            / l:variable() ss() "+=" ss() r:expression() {Tree::AssignAdd(l.into(), r.into())}
-            / l:variable() ss() "-=" ss() r:expression() {Tree::AssignSub(l.into(), r.into())}
-            / l:variable() ss() "*=" ss() r:expression() {Tree::AssignMul(l.into(), r.into())}
-            / l:variable() ss() "/=" ss() r:expression() {Tree::AssignDiv(l.into(), r.into())}
-            / l:variable() ss() "%=" ss() r:expression() {Tree::AssignMod(l.into(), r.into())}
-            / l:variable() ss() "^=" ss() r:expression() {Tree::AssignExp(l.into(), r.into())}
-        rule expression() -> Tree = precedence!{
-            l:@ ss() "and" ss() r:(@) {Tree::And(l.into(), r.into())}
-            l:@ ss() "or" ss() r:(@) {Tree::Or(l.into(), r.into())}
+        rule assignment() -> Spanned<Tree> =
+            l:variable() ss() "=" ss() r:expression() {binary(l, r, Tree::Assign)}
+            / l:variable() ss() "+=" ss() r:expression() {binary(l, r, Tree::AssignAdd)}
+            / l:variable() ss() "-=" ss() r:expression() {binary(l, r, Tree::AssignSub)}
+            / l:variable() ss() "*=" ss() r:expression() {binary(l, r, Tree::AssignMul)}
+            / l:variable() ss() "/=" ss() r:expression() {binary(l, r, Tree::AssignDiv)}
+            / l:variable() ss() "%=" ss() r:expression() {binary(l, r, Tree::AssignMod)}
+            / l:variable() ss() "^=" ss() r:expression() {binary(l, r, Tree::AssignExp)}
+        // from lowest to highest precedence, all binary operators are left associative
+        rule expression() -> Spanned<Tree> = precedence!{
+            l:(@) ss() kw("or") ss() r:@ {binary(l, r, Tree::Or)}
             --
-            "not" ss() r:(@) {Tree::Not(r.into())}
+            l:(@) ss() kw("and") ss() r:@ {binary(l, r, Tree::And)}
             --
-            l:(@) ss() "+" ss() r:@ {Tree::Add(l.into(), r.into())}
-            l:(@) ss() "-" ss() r:@ {Tree::Sub(l.into(), r.into())}
+            s:position!() kw("not") ss() r:(@) {unary(s, r, Tree::Not)}
             --
-            l:(@) ss() "==" ss() r:@ {Tree::Eq(l.into(), r.into())}
-            l:(@) ss() "!=" ss() r:@ {Tree::Ne(l.into(), r.into())}
-            l:(@) ss() "<" ss() r:@ {Tree::Lt(l.into(), r.into())}
-            l:(@) ss() ">" ss() r:@ {Tree::Gt(l.into(), r.into())}
-            l:(@) ss() "<=" ss() r:@ {Tree::Lte(l.into(), r.into())}
-            l:(@) ss() ">=" ss() r:@ {Tree::Gte(l.into(), r.into())}
+            l:(@) ss() "==" ss() r:@ {binary(l, r, Tree::Eq)}
+            l:(@) ss() "!=" ss() r:@ {binary(l, r, Tree::Ne)}
+            l:(@) ss() "<=" ss() r:@ {binary(l, r, Tree::Lte)}
+            l:(@) ss() ">=" ss() r:@ {binary(l, r, Tree::Gte)}
+            l:(@) ss() "<" ss() r:@ {binary(l, r, Tree::Lt)}
+            l:(@) ss() ">" ss() r:@ {binary(l, r, Tree::Gt)}
             --
-            l:(@) ss() "*" ss() r:@ {Tree::Mul(l.into(), r.into())}
-            l:(@) ss() "/" ss() r:@ {Tree::Div(l.into(), r.into())}
-            l:(@) ss() "%" ss() r:@ {Tree::Mod(l.into(), r.into())}
+            l:(@) ss() "+" ss() r:@ {binary(l, r, Tree::Add)}
+            l:(@) ss() "-" !"-" ss() r:@ {binary(l, r, Tree::Sub)}
             --
-            l:@ ss() "^" ss() r:(@) {Tree::Exp(l.into(), r.into())}
+            l:(@) ss() "*" ss() r:@ {binary(l, r, Tree::Mul)}
+            l:(@) ss() "/" ss() r:@ {binary(l, r, Tree::Div)}
+            l:(@) ss() "%" ss() r:@ {binary(l, r, Tree::Mod)}
             --
-            "abs" ss() r:(@) {Tree::Abs(r.into())}
-            "sqrt" ss() r:(@) {Tree::Sqrt(r.into())}
-            "sin" ss() r:(@) {Tree::Sin(r.into())}
-            "asin" ss() r:(@) {Tree::Asin(r.into())}
-            "cos" ss() r:(@) {Tree::Cos(r.into())}
-            "acos" ss() r:(@) {Tree::Acos(r.into())}
-            "tan" ss() r:(@) {Tree::Tan(r.into())}
-            "atan" ss() r:(@) {Tree::Atan(r.into())}
+            l:(@) ss() "^" ss() r:@ {binary(l, r, Tree::Exp)}
             --
-            l:litteral() {l}
-            "++" ss() r:(@) {Tree::PreInc(r.into())}
-            "--" ss() r:(@) {Tree::PreDec(r.into())}
-            "-" r:@ {Tree::Neg(r.into())}
+            s:position!() kw("abs") ss() r:(@) {unary(s, r, Tree::Abs)}
+            s:position!() kw("sqrt") ss() r:(@) {unary(s, r, Tree::Sqrt)}
+            s:position!() kw("sin") ss() r:(@) {unary(s, r, Tree::Sin)}
+            s:position!() kw("asin") ss() r:(@) {unary(s, r, Tree::Asin)}
+            s:position!() kw("cos") ss() r:(@) {unary(s, r, Tree::Cos)}
+            s:position!() kw("acos") ss() r:(@) {unary(s, r, Tree::Acos)}
+            s:position!() kw("tan") ss() r:(@) {unary(s, r, Tree::Tan)}
+            s:position!() kw("atan") ss() r:(@) {unary(s, r, Tree::Atan)}
+            s:position!() "-" !("-" / "." / digit()) ss() r:(@) {unary(s, r, Tree::Neg)}
             --
-            l:(@) ss() "++" {Tree::PostInc(l.into())}
-            l:(@) ss() "--" {Tree::PostDec(l.into())}
+            l:(@) ss() "!" !"=" e:position!() {postfix(l, e, Tree::Fac)}
             --
-            l:@ ss() "!" {Tree::Fac(l.into())}
-            "(" ss() e:expression() ss() ")" {e}
+            s:position!() "++" ss() v:variable() {unary(s, v, Tree::PreInc)}
+            s:position!() "--" ss() v:variable() {unary(s, v, Tree::PreDec)}
+            v:variable() ss() "++" e:position!() {postfix(v, e, Tree::PostInc)}
+            v:variable() ss() "--" e:position!() {postfix(v, e, Tree::PostDec)}
+            l:spanned(<litteral()>) {l}
+            s:position!() "(" ss() e:expression() ss() ")" end:position!() {Spanned::new(e.node, Span::new(s, end))}
             v:variable() {v}
         }
 
-        rule comment() -> Tree = "//" c:$(([^'\n']/ [^_])* ) {Tree::Comment(c.to_string())}
-        rule variable() -> Tree =
-            ":" s:$(b:alphanumeric()*) {Tree::GlobalVariable(get_global(s))}
-            / !("if"/ "else" / "end"/ "goto") s:$((a:alpha() b:alphanumeric()*)) {Tree::LocalVariable(get_local(s))}
+        rule spanned<T>(r: rule<T>) -> Spanned<T> = s:position!() n:r() e:position!() {Spanned::new(n, Span::new(s, e))}
+        rule comment() -> Spanned<Tree> = spanned(<"//" c:$(([^'\n']/ [^_])* ) {Tree::Comment(c.to_string())}>)
+        rule variable() -> Spanned<Tree> = spanned(<
+            ":" s:$(b:alphanumeric()*) {Tree::GlobalVariable(s.to_lowercase())}
+            / !quiet!{keyword()} s:$((a:alpha() b:alphanumeric()*)) {Tree::LocalVariable(s.to_lowercase())}>)
         rule litteral() -> Tree =
-            "-" d:$(digit()*) "." r:$(digit()+) {let d : i64 = ("-".to_string()+d).parse().unwrap();let r: i64 = match r.len() {1 => r.parse::<i64>().unwrap() * 100,2 => r.parse::<i64>().unwrap() * 10,_ => r[0..r.len().min(3)].parse().unwrap(),};Tree::Numerical((d * 1000).saturating_sub(r))}
-            / "-" d:$(digit()+) {let d : i64 = ("-".to_string()+d).parse().unwrap();Tree::Numerical(d.clamp(-9_223_372_036_854_775,9_223_372_036_854_775) * 1000)}
-            / d:$(digit()*) "." r:$(digit()+) {let d : i64 = d.parse().unwrap();let r: i64 = match r.len() {1 => r.parse::<i64>().unwrap() * 100,2 => r.parse::<i64>().unwrap() * 10,_ => r[0..r.len().min(3)].parse().unwrap(),};Tree::Numerical((d * 1000).saturating_add(r))}
-            / d:$(digit()+) {let d : i64 = d.parse().unwrap();Tree::Numerical(d * 1000)}
+            n:"-"? d:$(digit()*) "." r:$(digit()+) {Tree::Numerical(fixed(n.is_some(), d, r))}
+            / n:"-"? d:$(digit()+) {Tree::Numerical(fixed(n.is_some(), d, ""))}
             / "\"" s:$([^ '"']*) "\"" {Tree::String(s.to_string())}
     }
 }
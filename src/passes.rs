@@ -0,0 +1,429 @@
+use std::fmt::Display;
+
+use crate::analysis::analyze;
+use crate::analysis::Bool;
+use crate::analysis::Type;
+use crate::ast::Span;
+use crate::dataflow::entry_types;
+use crate::fold::fold;
+use crate::opt::Passes;
+use crate::verify::stack_effect;
+use crate::verify::verify;
+use crate::verify::VerifyError;
+use crate::vm::Instruction;
+use crate::Code;
+
+/// What an optimization pass did to a line.
+#[derive(Debug, Clone)]
+pub struct PassReport {
+    pub name: &'static str,
+    pub before: Vec<Instruction>,
+    pub after: Vec<Instruction>,
+    /// Why instructions were changed or removed.
+    pub notes: Vec<String>,
+    /// The pass produced invalid bytecode, the line was kept as it was before.
+    pub error: Option<VerifyError>,
+}
+
+impl PassReport {
+    pub fn changed(&self) -> bool {
+        format!("{:?}", self.before) != format!("{:?}", self.after)
+    }
+}
+
+/// Every optimization pass run on the 1-indexed line `line`, in order.
+#[derive(Debug, Clone)]
+pub struct LineReport {
+    pub line: usize,
+    pub source: String,
+    pub passes: Vec<PassReport>,
+}
+
+impl Display for LineReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "line {} `{}`", self.line, self.source)?;
+        for pass in &self.passes {
+            if let Some(err) = &pass.error {
+                writeln!(f, "  {}: result dropped, {}", pass.name, err)?;
+            } else if !pass.changed() {
+                writeln!(f, "  {}: unchanged", pass.name)?;
+            } else {
+                writeln!(
+                    f,
+                    "  {}: {} -> {} instructions",
+                    pass.name,
+                    pass.before.len(),
+                    pass.after.len()
+                )?;
+                for note in &pass.notes {
+                    writeln!(f, "    {}", note)?;
+                }
+                writeln!(f, "    before: {:?}", pass.before)?;
+                writeln!(f, "    after: {:?}", pass.after)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What a pass knows of the line it transforms.
+struct Context<'a> {
+    source: &'a str,
+    /// Types of the variables when the line starts.
+    entry: &'a [Type],
+}
+
+impl Context<'_> {
+    fn text(&self, span: Span) -> &str {
+        self.source.get(span.start..span.end).unwrap_or_default()
+    }
+}
+
+trait Pass {
+    fn name(&self) -> &'static str;
+
+    /// Read the types of the variables when the line starts.
+    fn needs_types(&self) -> bool {
+        false
+    }
+
+    /// Transform `line`, explaining every change in `notes`.
+    fn run(&self, line: Code, cx: &Context, notes: &mut Vec<String>) -> Code;
+}
+
+/// Runs the enabled optimization passes on every line of a program.
+pub struct PassManager {
+    passes: Vec<Box<dyn Pass>>,
+    dataflow: bool,
+}
+
+impl PassManager {
+    pub fn new(passes: Passes) -> Self {
+        let all: Vec<(bool, Box<dyn Pass>)> = vec![
+            (passes.fold, Box::new(Fold)),
+            (passes.dead_branches, Box::new(DeadBranches)),
+            (passes.failing_tail, Box::new(FailingTail)),
+            (passes.stack_cleanup, Box::new(StackCleanup)),
+        ];
+        Self {
+            passes: all
+                .into_iter()
+                .filter(|(enabled, _)| *enabled)
+                .map(|(_, pass)| pass)
+                .collect(),
+            dataflow: passes.dataflow,
+        }
+    }
+
+    /// Optimize `lines`, which use `memory` variable slots, and report what
+    /// every pass did to each of them.
+    ///
+    /// A pass producing invalid bytecode for a line is ignored for that line.
+    pub fn run(
+        &self,
+        mut lines: Vec<Code>,
+        sources: &[&str],
+        memory: usize,
+        globals: &[usize],
+    ) -> (Vec<Code>, Vec<LineReport>) {
+        let mut reports: Vec<LineReport> = (0..lines.len())
+            .map(|i| LineReport {
+                line: i + 1,
+                source: sources.get(i).unwrap_or(&"").to_string(),
+                passes: vec![],
+            })
+            .collect();
+        let mut entries: Option<Vec<Option<Vec<Type>>>> = None;
+
+        for pass in &self.passes {
+            if pass.needs_types() && entries.is_none() {
+                entries = Some(if self.dataflow {
+                    entry_types(&lines, memory, globals)
+                } else {
+                    vec![None; lines.len()]
+                });
+            }
+            for (i, line) in lines.iter_mut().enumerate() {
+                // a line no run reaches can still be selected by hand
+                let entry = entries
+                    .as_ref()
+                    .and_then(|entries| entries[i].clone())
+                    .unwrap_or_else(|| vec![Type::Int(Bool::Unknown); memory]);
+                let cx = Context {
+                    source: &reports[i].source,
+                    entry: &entry,
+                };
+                let mut notes = vec![];
+                let after = pass.run(line.clone(), &cx, &mut notes);
+                let instructions = |line: &Code| -> Vec<Instruction> {
+                    line.iter().map(|(i, _)| i.clone()).collect()
+                };
+                let error = verify(&instructions(&after), memory).err();
+                let report = PassReport {
+                    name: pass.name(),
+                    before: instructions(line),
+                    after: instructions(&after),
+                    notes,
+                    error,
+                };
+                if report.error.is_none() {
+                    *line = after;
+                }
+                reports[i].passes.push(report);
+            }
+        }
+        (lines, reports)
+    }
+}
+
+/// Remove the instructions flagged in `dead`, moving the jumps over them.
+fn remove(line: Code, dead: &[bool]) -> Code {
+    // new index of every instruction and of the end of the line
+    let mut index = Vec::with_capacity(line.len() + 1);
+    let mut kept = 0;
+    for dead in dead {
+        index.push(kept);
+        if !dead {
+            kept += 1;
+        }
+    }
+    index.push(kept);
+    let target = |i: usize, rel: usize| index[(i + 1 + rel).min(line.len())] - index[i] - 1;
+
+    line.iter()
+        .enumerate()
+        .filter(|(i, _)| !dead[*i])
+        .map(|(i, (inst, span))| {
+            let inst = match inst {
+                Instruction::Jump(rel) => Instruction::Jump(target(i, *rel)),
+                Instruction::JumpFalse(rel) => Instruction::JumpFalse(target(i, *rel)),
+                inst => inst.clone(),
+            };
+            (inst, *span)
+        })
+        .collect()
+}
+
+/// Get the instructions some jump lands on.
+fn jump_targets(line: &Code) -> Vec<bool> {
+    let mut targets = vec![false; line.len() + 1];
+    for (i, (inst, _)) in line.iter().enumerate() {
+        if let Instruction::Jump(rel) | Instruction::JumpFalse(rel) = inst {
+            if let Some(target) = targets.get_mut(i + 1 + rel) {
+                *target = true;
+            }
+        }
+    }
+    targets
+}
+
+/// Computes the operations on constants once.
+struct Fold;
+
+impl Pass for Fold {
+    fn name(&self) -> &'static str {
+        "fold"
+    }
+
+    fn run(&self, line: Code, cx: &Context, notes: &mut Vec<String>) -> Code {
+        let mut changes: Vec<(Span, &str)> = vec![];
+        let line = fold(line, &mut changes);
+        // only the outermost of nested constants
+        for (span, message) in &changes {
+            let nested = changes.iter().any(|(other, m)| {
+                m == message && other != span && other.start <= span.start && span.end <= other.end
+            });
+            if !nested {
+                notes.push(format!("`{}` {}", cx.text(*span), message));
+            }
+        }
+        line
+    }
+}
+
+/// Removes the code no path reaches and the branches taken on a condition
+/// of a known truth.
+struct DeadBranches;
+
+impl Pass for DeadBranches {
+    fn name(&self) -> &'static str {
+        "dead-branches"
+    }
+
+    fn needs_types(&self) -> bool {
+        true
+    }
+
+    fn run(&self, mut line: Code, cx: &Context, notes: &mut Vec<String>) -> Code {
+        let analysis = analyze(&line, cx.entry);
+        let mut dead = vec![false; line.len()];
+        // an operation failing in the middle of an expression would fall
+        // through with values left on the stack, the code after it stays
+        let mut keep = false;
+        for (i, state) in analysis.states.iter().enumerate() {
+            if state.is_some() {
+                keep = analysis.fails[i] && !matches!(line[i].0, Instruction::SyntaxError);
+            } else {
+                dead[i] = !keep;
+            }
+        }
+
+        for (i, state) in analysis.states.iter().enumerate() {
+            if let (Instruction::JumpFalse(_), Some(state)) = (&line[i].0, state) {
+                let truth = match state.stack.last().map(|t| t.truth()) {
+                    Some(Bool::True) => "true",
+                    Some(Bool::False) => "false",
+                    _ => continue,
+                };
+                // the condition is the expression computed just before
+                let condition = line[i.saturating_sub(1)].1;
+                notes.push(format!(
+                    "condition of `{}` is always {}",
+                    cx.text(condition),
+                    truth
+                ));
+                line[i].0 = Instruction::Pop;
+            }
+        }
+
+        let mut i = 0;
+        while i < line.len() {
+            if !dead[i] {
+                i += 1;
+                continue;
+            }
+            // the jump over an else block spans the whole if
+            let spans: Vec<Span> = line[i..]
+                .iter()
+                .zip(&dead[i..])
+                .take_while(|(_, dead)| **dead)
+                .filter(|((inst, _), _)| !matches!(inst, Instruction::Jump(_)))
+                .map(|((_, span), _)| *span)
+                .collect();
+            let run = dead[i..].iter().take_while(|d| **d).count();
+            if let (Some(start), Some(end)) = (
+                spans.iter().map(|s| s.start).min(),
+                spans.iter().map(|s| s.end).max(),
+            ) {
+                notes.push(format!("`{}` never runs", cx.text(Span::new(start, end))));
+            }
+            i += run;
+        }
+
+        let line = remove(line, &dead);
+        // a jump over a removed else block goes nowhere
+        let dead: Vec<bool> = line
+            .iter()
+            .map(|(inst, _)| matches!(inst, Instruction::Jump(0)))
+            .collect();
+        remove(line, &dead)
+    }
+}
+
+/// Cuts a line after an operation certain to fail on a string, outside of any
+/// branch.
+///
+/// The operation stays, so the line still ends with the same runtime error.
+/// Nothing after it ever runs.
+struct FailingTail;
+
+impl Pass for FailingTail {
+    fn name(&self) -> &'static str {
+        "failing-tail"
+    }
+
+    fn needs_types(&self) -> bool {
+        true
+    }
+
+    fn run(&self, line: Code, cx: &Context, notes: &mut Vec<String>) -> Code {
+        let analysis = analyze(&line, cx.entry);
+        let in_branch = |i: usize| {
+            line[..i]
+                .iter()
+                .enumerate()
+                .any(|(c, (inst, _))| match inst {
+                    Instruction::Jump(rel) | Instruction::JumpFalse(rel) => c + 1 + rel > i,
+                    _ => false,
+                })
+        };
+        let failing = (0..line.len()).find(|i| {
+            analysis.fails[*i] && !matches!(line[*i].0, Instruction::SyntaxError) && !in_branch(*i)
+        });
+        match failing {
+            Some(i) => {
+                notes.push(format!(
+                    "`{}` always fails on a string, the line stops there",
+                    cx.text(line[i].1)
+                ));
+                let dead: Vec<bool> = (0..line.len()).map(|c| c > i).collect();
+                remove(line, &dead)
+            }
+            None => line,
+        }
+    }
+}
+
+/// Removes the values pushed only to be dropped.
+struct StackCleanup;
+
+impl Pass for StackCleanup {
+    fn name(&self) -> &'static str {
+        "stack-cleanup"
+    }
+
+    fn run(&self, line: Code, cx: &Context, notes: &mut Vec<String>) -> Code {
+        let targets = jump_targets(&line);
+        let pure = |p: usize| {
+            matches!(
+                line[p].0,
+                Instruction::PushValue(_) | Instruction::Push(_) | Instruction::Dup
+            )
+        };
+        let mut dead = vec![false; line.len()];
+        // instruction that pushed every value of the stack, if it can go
+        let mut stack: Vec<Option<usize>> = vec![];
+        // values pushed before a jump target also come from another path
+        let mut barrier = 0;
+
+        for (i, (inst, _)) in line.iter().enumerate() {
+            if targets[i] {
+                barrier = i;
+            }
+            match inst {
+                Instruction::Pop => {
+                    if let Some(Some(p)) = stack.pop() {
+                        if pure(p) && p >= barrier {
+                            dead[p] = true;
+                            dead[i] = true;
+                            notes.push(format!("value of `{}` is never read", cx.text(line[p].1)));
+                        }
+                    }
+                }
+                // only the copy below can be dropped along with the `Dup`
+                Instruction::Dup => {
+                    stack.pop();
+                    stack.push(Some(i));
+                    stack.push(None);
+                }
+                _ => {
+                    let (pops, pushes) = stack_effect(inst);
+                    for _ in 0..pops {
+                        stack.pop();
+                    }
+                    for _ in 0..pushes {
+                        stack.push(Some(i));
+                    }
+                }
+            }
+        }
+        // the values left when the line ends
+        for p in stack.into_iter().flatten() {
+            if pure(p) && p >= barrier && !targets[line.len()] {
+                dead[p] = true;
+                notes.push(format!("value of `{}` is never read", cx.text(line[p].1)));
+            }
+        }
+        remove(line, &dead)
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+
+/// Maps variable names of a single script to slots of its variable memory.
+///
+/// Locals and `:globals` live in separate namespaces but share the same
+/// address space, so `a` and `:a` get two different slots.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    globals: BTreeMap<String, usize>,
+    locals: BTreeMap<String, usize>,
+    /// Slots holding values of the compiler, shared by every line.
+    temps: Vec<usize>,
+    len: usize,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the slot of a local, allocating one on first use.
+    pub fn local(&mut self, name: &str) -> usize {
+        Self::resolve(&mut self.locals, &mut self.len, name)
+    }
+
+    /// Get the slot of a global, allocating one on first use.
+    pub fn global(&mut self, name: &str) -> usize {
+        Self::resolve(&mut self.globals, &mut self.len, name)
+    }
+
+    /// Get the slot of the temporary `n`, allocating the slots up to it on
+    /// first use. Temporaries have no name and no line reads one another
+    /// line wrote.
+    pub fn temp(&mut self, n: usize) -> usize {
+        while self.temps.len() <= n {
+            self.temps.push(self.len);
+            self.len += 1;
+        }
+        self.temps[n]
+    }
+
+    /// Get the slot of an already known global.
+    pub fn get_global(&self, name: &str) -> Option<usize> {
+        self.globals.get(&name.to_lowercase()).copied()
+    }
+
+    /// Iterate over the known globals and their slots.
+    pub fn globals(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.globals.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Iterate over the known locals and their slots.
+    pub fn locals(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.locals.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+
+    /// Number of allocated slots.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn resolve(map: &mut BTreeMap<String, usize>, len: &mut usize, name: &str) -> usize {
+        let name = name.to_lowercase();
+        if let Some(adress) = map.get(&name) {
+            return *adress;
+        }
+        map.insert(name, *len);
+        *len += 1;
+        *len - 1
+    }
+}
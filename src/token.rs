@@ -0,0 +1,122 @@
+use crate::ast::Span;
+use crate::parser::KEYWORDS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A reserved word, including the prefix operators like `abs`.
+    Keyword,
+    Global,
+    Local,
+    Number,
+    String,
+    /// Operators, assignments and parentheses. A minus sign is always an
+    /// operator, even when the parser reads it as part of a number.
+    Operator,
+    Comment,
+    /// Characters that cannot start a token, or a string left open.
+    Error,
+}
+
+/// A token of a line and the bytes it covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+impl Token {
+    /// Get the text of the token in `line`.
+    pub fn text<'a>(&self, line: &'a str) -> &'a str {
+        line.get(self.span.start..self.span.end).unwrap_or_default()
+    }
+}
+
+const OPERATORS: [&str; 24] = [
+    "++", "--", "+=", "-=", "*=", "/=", "%=", "^=", "==", "!=", "<=", ">=", "=", "+", "-", "*",
+    "/", "%", "^", "<", ">", "!", "(", ")",
+];
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Split a line into tokens, spaces and tabs between them are skipped.
+///
+/// Unlike the parser, this never fails: what it does not recognise becomes
+/// an [`TokenKind::Error`] token, so a broken line still highlights.
+pub fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut pos = 0;
+    while let Some(c) = line[pos..].chars().next() {
+        let rest = &line[pos..];
+        let start = pos;
+        let word_end = |from: usize| {
+            from + line[from..]
+                .find(|c: char| !is_word(c))
+                .unwrap_or(line.len() - from)
+        };
+        let digits_end = |from: usize| {
+            from + line[from..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(line.len() - from)
+        };
+
+        let kind = if c == ' ' || c == '\t' {
+            pos += 1;
+            continue;
+        } else if rest.starts_with("//") {
+            pos = line.len();
+            TokenKind::Comment
+        } else if c == ':' {
+            pos = word_end(pos + 1);
+            TokenKind::Global
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            pos = word_end(pos);
+            let word = line[start..pos].to_lowercase();
+            if KEYWORDS.contains(&word.as_str()) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Local
+            }
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            pos = digits_end(pos);
+            if line[pos..].starts_with('.')
+                && line[pos + 1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                pos = digits_end(pos + 1);
+            }
+            TokenKind::Number
+        } else if c == '"' {
+            match rest[1..].find('"') {
+                Some(end) => {
+                    pos += end + 2;
+                    TokenKind::String
+                }
+                None => {
+                    pos = line.len();
+                    TokenKind::Error
+                }
+            }
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+            pos += op.len();
+            TokenKind::Operator
+        } else {
+            pos += c.len_utf8();
+            // a run of unknown characters is a single error
+            if let Some(last) = tokens.last_mut() {
+                if last.kind == TokenKind::Error && last.span.end == start {
+                    last.span.end = pos;
+                    continue;
+                }
+            }
+            TokenKind::Error
+        };
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, pos),
+        });
+    }
+    tokens
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use crate::vm::Instruction;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VerifyErrorKind {
+    /// A jump lands past the end of the line.
+    JumpOutOfRange(usize),
+    /// An instruction pops more values than the stack holds.
+    StackUnderflow,
+    /// Two paths reach the same instruction with different stack depths.
+    StackMismatch { expected: usize, found: usize },
+    /// A `Push` or `Store` uses a slot outside of the variable memory.
+    UnallocatedAdress(usize),
+}
+
+impl Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyErrorKind::JumpOutOfRange(target) => {
+                write!(f, "jump to {} is out of the line", target)
+            }
+            VerifyErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            VerifyErrorKind::StackMismatch { expected, found } => write!(
+                f,
+                "stack depth {} does not match depth {} of another path",
+                found, expected
+            ),
+            VerifyErrorKind::UnallocatedAdress(adress) => {
+                write!(f, "adress {} is not allocated", adress)
+            }
+        }
+    }
+}
+
+/// Malformed bytecode found by [`verify`] at the instruction `instruction`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyError {
+    pub kind: VerifyErrorKind,
+    pub instruction: usize,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "invalid instruction {} : {}",
+            self.instruction, self.kind
+        )
+    }
+}
+
+impl Error for VerifyError {}
+
+/// Check that a compiled line is safe to run against `memory` variable slots.
+///
+/// Every jump must land inside the line or right after its end, the stack
+/// must never underflow and must have the same depth on every path reaching
+/// an instruction.
+pub fn verify(line: &[Instruction], memory: usize) -> Result<(), VerifyError> {
+    let mut depths: Vec<Option<usize>> = vec![None; line.len() + 1];
+    let mut todo = vec![(0, 0)];
+
+    while let Some((pc, depth)) = todo.pop() {
+        let error = |kind| VerifyError {
+            kind,
+            instruction: pc,
+        };
+        match depths[pc] {
+            Some(expected) if expected != depth => {
+                return Err(error(VerifyErrorKind::StackMismatch {
+                    expected,
+                    found: depth,
+                }))
+            }
+            Some(_) => continue,
+            None => depths[pc] = Some(depth),
+        }
+        let instruction = match line.get(pc) {
+            Some(instruction) => instruction,
+            None => continue,
+        };
+
+        if let Instruction::Push(adress) | Instruction::Store(adress) = instruction {
+            if *adress >= memory {
+                return Err(error(VerifyErrorKind::UnallocatedAdress(*adress)));
+            }
+        }
+
+        let (pops, pushes) = stack_effect(instruction);
+        if depth < pops {
+            return Err(error(VerifyErrorKind::StackUnderflow));
+        }
+        let depth = depth - pops + pushes;
+
+        // a target past `usize::MAX` is reported as `usize::MAX`
+        let mut next = |target: Option<usize>| match target {
+            Some(target) if target <= line.len() => {
+                todo.push((target, depth));
+                Ok(())
+            }
+            target => Err(error(VerifyErrorKind::JumpOutOfRange(
+                target.unwrap_or(usize::MAX),
+            ))),
+        };
+        let after = |rel: usize| pc.checked_add(1).and_then(|p| p.checked_add(rel));
+        match instruction {
+            Instruction::Goto | Instruction::SyntaxError => (),
+            Instruction::Jump(rel) => next(after(*rel))?,
+            Instruction::JumpFalse(rel) => {
+                next(after(0))?;
+                next(after(*rel))?;
+            }
+            _ => next(after(0))?,
+        }
+    }
+    Ok(())
+}
+
+/// Get how many values `instruction` pops and pushes.
+pub fn stack_effect(instruction: &Instruction) -> (usize, usize) {
+    match instruction {
+        Instruction::Dup => (1, 2),
+        Instruction::Pop | Instruction::Store(_) | Instruction::Goto => (1, 0),
+        Instruction::PushValue(_) | Instruction::Push(_) => (0, 1),
+        Instruction::Jump(_) | Instruction::SyntaxError => (0, 0),
+        Instruction::JumpFalse(_) => (1, 0),
+        Instruction::Or
+        | Instruction::And
+        | Instruction::Eq
+        | Instruction::Ne
+        | Instruction::Lt
+        | Instruction::Gt
+        | Instruction::Lte
+        | Instruction::Gte
+        | Instruction::Add
+        | Instruction::Sub
+        | Instruction::Mul
+        | Instruction::Div
+        | Instruction::Mod
+        | Instruction::Exp => (2, 1),
+        Instruction::Abs
+        | Instruction::Sqrt
+        | Instruction::Sin
+        | Instruction::Cos
+        | Instruction::Tan
+        | Instruction::Asin
+        | Instruction::Acos
+        | Instruction::Atan
+        | Instruction::Not
+        | Instruction::Fac
+        | Instruction::Neg
+        | Instruction::Inc
+        | Instruction::Dec => (1, 1),
+    }
+}
@@ -1,3 +1,8 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use yolol_devices::value::ValueTrait;
+use yolol_devices::value::YololInt;
 use yolol_devices::value::YololValue;
 
 #[derive(Debug, Clone)]
@@ -37,13 +42,381 @@ pub enum Instruction {
     Atan,
     Not,
     Fac,
+    Neg,
     Inc,
     Dec,
+    ///abort the line on a segment that failed to parse
+    SyntaxError,
 }
 
+/// Execution engine running compiled lines against its own variable memory.
+///
+/// Each line is executed as a whole by [`VM::step`], which then moves the
+/// line pointer to the next line or to the `goto` target.
 #[derive(Debug, Default)]
 pub struct VM {
-    pc: isize,
+    lines: Vec<Vec<Instruction>>,
+    variables: Vec<YololValue>,
+    stack: Vec<YololValue>,
+    pc: usize,
+    line: usize,
+    last_error: Option<RuntimeError>,
+}
+
+impl VM {
+    /// Create an engine for `lines` with `memory` zeroed variable slots.
+    pub fn new(lines: Vec<Vec<Instruction>>, memory: usize) -> Self {
+        Self {
+            lines,
+            variables: vec![YololValue::default(); memory],
+            stack: Vec::with_capacity(32),
+            ..Self::default()
+        }
+    }
+
+    pub fn lines(&self) -> &[Vec<Instruction>] {
+        &self.lines
+    }
+
+    /// Get the 0-indexed line executed by the next step.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn set_line(&mut self, line: usize) {
+        self.line = line;
+    }
+
+    /// Get the instruction pointer within the current line.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    pub fn variables(&self) -> &[YololValue] {
+        &self.variables
+    }
+
+    pub fn variable(&self, adress: usize) -> Option<&YololValue> {
+        self.variables.get(adress)
+    }
+
+    pub fn set_variable(&mut self, adress: usize, value: YololValue) {
+        if let Some(variable) = self.variables.get_mut(adress) {
+            *variable = value;
+        }
+    }
+
+    /// Get the runtime error that aborted the line executed by the last step.
+    pub fn last_error(&self) -> Option<&RuntimeError> {
+        self.last_error.as_ref()
+    }
+
+    /// Execute the current line and move to the next one.
+    pub fn step(&mut self) {
+        if self.line >= self.lines.len() {
+            self.line = 0;
+        }
+        if self.lines.get(self.line).is_none_or(|l| l.is_empty()) {
+            self.last_error = None;
+            self.line += 1;
+            return;
+        }
+
+        match self.run() {
+            Ok(Some(YololValue::Int(v))) => {
+                self.last_error = None;
+                let v: i64 = (&v).into();
+                self.line = (v - 1).clamp(0, self.lines.len() as i64 - 1) as usize;
+            }
+            result => {
+                self.last_error = result.err();
+                self.line += 1;
+            }
+        }
+    }
+
+    /// Execute the current line.
+    ///
+    /// Returns the goto target if the line jumped, a runtime error aborts the
+    /// rest of the line like in game.
+    pub fn run(&mut self) -> Result<Option<YololValue>, RuntimeError> {
+        self.stack.clear();
+        self.pc = 0;
+        self.exec().map_err(|kind| RuntimeError {
+            kind,
+            line: self.line + 1,
+            instruction: self.pc,
+        })
+    }
+
+    fn exec(&mut self) -> Result<Option<YololValue>, RuntimeErrorKind> {
+        let instructions = match self.lines.get(self.line) {
+            Some(instructions) => instructions,
+            None => return Ok(None),
+        };
+        while instructions.len() > self.pc {
+            let instruction = &instructions[self.pc];
+            match instruction {
+                Instruction::PushValue(value) => self.stack.push(value.clone()),
+                Instruction::Push(adress) => {
+                    let value = self.variables.get(*adress).cloned().unwrap_or_default();
+                    self.stack.push(value);
+                }
+                Instruction::Store(adress) => {
+                    let value = pop(&mut self.stack)?;
+                    if let Some(variable) = self.variables.get_mut(*adress) {
+                        *variable = value;
+                    }
+                }
+                Instruction::SyntaxError => return Err(RuntimeErrorKind::SyntaxError),
+                Instruction::Goto => {
+                    return match pop(&mut self.stack)? {
+                        YololValue::String(_) => Err(RuntimeErrorKind::InvalidGoto),
+                        v => Ok(Some(v)),
+                    }
+                }
+                Instruction::Or => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = a.or(&b)
+                }
+                Instruction::And => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = a.and(&b)
+                }
+                Instruction::Eq => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a == b).into()
+                }
+                Instruction::Ne => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a != b).into()
+                }
+                Instruction::Lt => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a < b).into()
+                }
+                Instruction::Gt => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a > b).into()
+                }
+                Instruction::Lte => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a <= b).into()
+                }
+                Instruction::Gte => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (*a >= b).into()
+                }
+                Instruction::Add => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = &*a + &b
+                }
+                Instruction::Sub => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (&*a - &b).ok_or_else(|| binary_error(instruction, a, &b))?
+                }
+                Instruction::Mul => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (&*a * &b).ok_or_else(|| binary_error(instruction, a, &b))?
+                }
+                Instruction::Div => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (&*a / &b).ok_or_else(|| binary_error(instruction, a, &b))?
+                }
+                Instruction::Mod => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = (&*a % &b).ok_or_else(|| binary_error(instruction, a, &b))?
+                }
+                Instruction::Exp => {
+                    let b = pop(&mut self.stack)?;
+                    let a = last(&mut self.stack)?;
+                    *a = a.pow(&b).ok_or_else(|| binary_error(instruction, a, &b))?
+                }
+                Instruction::Abs => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.abs().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Sqrt => {
+                    let v = last(&mut self.stack)?;
+                    in_domain(instruction, v)?;
+                    *v = v.sqrt().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Sin => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.sin().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Cos => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.cos().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Tan => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.tan().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Asin => {
+                    let v = last(&mut self.stack)?;
+                    in_domain(instruction, v)?;
+                    *v = v.asin().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Acos => {
+                    let v = last(&mut self.stack)?;
+                    in_domain(instruction, v)?;
+                    *v = v.acos().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Atan => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.atan().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Not => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.not();
+                }
+                Instruction::Fac => {
+                    let v = last(&mut self.stack)?;
+                    *v = v.fac().ok_or_else(|| unary_error(instruction, v))?
+                }
+                Instruction::Neg => match last(&mut self.stack)? {
+                    YololValue::Int(v) => *v = &YololInt::default() - v,
+                    YololValue::String(_) => return Err(RuntimeErrorKind::InvalidStringOp),
+                },
+                Instruction::Inc => {
+                    let i = last(&mut self.stack)?;
+                    i.pre_inc();
+                }
+                Instruction::Dec => match last(&mut self.stack)? {
+                    YololValue::String(s) => {
+                        s.pop().ok_or(RuntimeErrorKind::InvalidStringOp)?;
+                    }
+                    i => {
+                        i.pre_dec();
+                    }
+                },
+                Instruction::Jump(i) => self.pc = self.pc.saturating_add(*i),
+                Instruction::JumpFalse(i) => {
+                    let b: bool = (&pop(&mut self.stack)?).into();
+                    if !b {
+                        self.pc = self.pc.saturating_add(*i);
+                    }
+                }
+                Instruction::Dup => {
+                    let v = last(&mut self.stack)?.clone();
+                    self.stack.push(v);
+                }
+                Instruction::Pop => {
+                    pop(&mut self.stack)?;
+                }
+            }
+            self.pc = self.pc.saturating_add(1);
+        }
+        Ok(None)
+    }
+}
+
+fn pop(stack: &mut Vec<YololValue>) -> Result<YololValue, RuntimeErrorKind> {
+    stack.pop().ok_or(RuntimeErrorKind::StackUnderflow)
+}
+
+fn last(stack: &mut [YololValue]) -> Result<&mut YololValue, RuntimeErrorKind> {
+    stack.last_mut().ok_or(RuntimeErrorKind::StackUnderflow)
+}
+
+fn binary_error(instruction: &Instruction, a: &YololValue, b: &YololValue) -> RuntimeErrorKind {
+    match (instruction, a, b) {
+        (_, YololValue::String(_), _) | (_, _, YololValue::String(_)) => {
+            RuntimeErrorKind::InvalidStringOp
+        }
+        (Instruction::Div, _, _) | (Instruction::Mod, _, _) => RuntimeErrorKind::DivideByZero,
+        _ => RuntimeErrorKind::DomainError,
+    }
+}
+
+/// Check the number given to `sqrt`, `asin` or `acos` is in the domain of the
+/// function, yolol-devices gives the smallest number instead of failing.
+fn in_domain(instruction: &Instruction, v: &YololValue) -> Result<(), RuntimeErrorKind> {
+    let v = match v {
+        YololValue::Int(v) => v,
+        YololValue::String(_) => return Ok(()),
+    };
+    let outside = match instruction {
+        Instruction::Sqrt => *v < YololInt::default(),
+        Instruction::Asin | Instruction::Acos => *v > 1.into() || *v < (-1).into(),
+        _ => false,
+    };
+    if outside {
+        return Err(RuntimeErrorKind::DomainError);
+    }
+    Ok(())
+}
+
+fn unary_error(instruction: &Instruction, v: &YololValue) -> RuntimeErrorKind {
+    match (instruction, v) {
+        (_, YololValue::String(_)) => RuntimeErrorKind::InvalidStringOp,
+        _ => RuntimeErrorKind::DomainError,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeErrorKind {
+    /// Division or modulo by zero.
+    DivideByZero,
+    /// Operation that is not defined for strings, like `"a" * 2`, `sqrt "a"` or
+    /// `--` on an empty string.
+    InvalidStringOp,
+    /// Numeric operand rejected by the operation, like `asin 2`.
+    DomainError,
+    /// `goto` with a string target.
+    InvalidGoto,
+    /// Malformed bytecode popped an empty stack.
+    StackUnderflow,
+    /// A segment of the line failed to parse.
+    SyntaxError,
+}
+
+impl Display for RuntimeErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuntimeErrorKind::DivideByZero => write!(f, "division by zero"),
+            RuntimeErrorKind::InvalidStringOp => write!(f, "invalid operation on a string"),
+            RuntimeErrorKind::DomainError => write!(f, "operand out of domain"),
+            RuntimeErrorKind::InvalidGoto => write!(f, "goto with a string"),
+            RuntimeErrorKind::StackUnderflow => write!(f, "stack underflow"),
+            RuntimeErrorKind::SyntaxError => write!(f, "syntax error"),
+        }
+    }
+}
+
+/// Error that aborted the execution of a line.
+///
+/// `line` is 1-indexed and `instruction` is the index of the faulting
+/// instruction in the compiled line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub kind: RuntimeErrorKind,
+    pub line: usize,
+    pub instruction: usize,
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "runtime error line {} instruction {} : {}",
+            self.line, self.instruction, self.kind
+        )
+    }
 }
 
-impl VM {}
+impl Error for RuntimeError {}
@@ -0,0 +1,89 @@
+use yolol_runner::ast::walk_tree;
+use yolol_runner::ast::Fold;
+use yolol_runner::ast::Span;
+use yolol_runner::ast::Spanned;
+use yolol_runner::ast::Tree;
+use yolol_runner::ast::Visitor;
+use yolol_runner::parse_program;
+
+#[derive(Default)]
+struct Globals(Vec<String>);
+
+impl Visitor for Globals {
+    fn visit_tree(&mut self, tree: &Spanned<Tree>) {
+        if let Tree::GlobalVariable(name) = &tree.node {
+            self.0.push(name.clone());
+        }
+        walk_tree(self, tree)
+    }
+}
+
+struct Rename;
+
+impl Fold for Rename {
+    fn fold_tree(&mut self, tree: Spanned<Tree>) -> Spanned<Tree> {
+        match tree.node {
+            Tree::LocalVariable(name) => {
+                Spanned::new(Tree::LocalVariable(name.to_uppercase()), tree.span)
+            }
+            _ => yolol_runner::ast::fold_tree(self, tree),
+        }
+    }
+}
+
+#[test]
+fn parse_lines() {
+    let program = parse_program(":a=1 b=2\n\nif :a then goto 1 end\n:a=)");
+    assert_eq!(program.lines.len(), 4);
+    assert_eq!(program.lines[0].len(), 2);
+    assert!(program.lines[1].is_empty());
+    assert!(matches!(program.lines[2][0].node, Tree::IfThen(_, _)));
+    assert_eq!(
+        program.lines[3],
+        [Spanned::new(
+            Tree::Error(":a=)".to_string()),
+            Span::new(0, 4)
+        )]
+    );
+    assert_eq!(program.diagnostics.len(), 1);
+    assert_eq!(program.diagnostics[0].line, 4);
+}
+
+#[test]
+fn spans() {
+    let program = parse_program("  :a = (1 + b) * 2");
+    let assign = &program.lines[0][0];
+    assert_eq!(assign.span, Span::new(2, 18));
+    if let Tree::Assign(a, v) = &assign.node {
+        assert_eq!(a.span, Span::new(2, 4));
+        assert_eq!(v.span, Span::new(7, 18));
+        assert_eq!(v.children()[0].span, Span::new(7, 14));
+    } else {
+        panic!("{:?}", assign);
+    }
+}
+
+#[test]
+fn visitor() {
+    let program = parse_program(":a=:b+1 if :c then :d++ else x=-:e end\ngoto :f");
+    let mut globals = Globals::default();
+    globals.visit_program(&program);
+    assert_eq!(globals.0, ["a", "b", "c", "d", "e", "f"]);
+}
+
+#[test]
+fn fold() {
+    let program = Rename.fold_program(parse_program("a=b+1 if c then d=2 end"));
+    let mut locals = vec![];
+    struct Locals<'a>(&'a mut Vec<String>);
+    impl Visitor for Locals<'_> {
+        fn visit_tree(&mut self, tree: &Spanned<Tree>) {
+            if let Tree::LocalVariable(name) = &tree.node {
+                self.0.push(name.clone());
+            }
+            walk_tree(self, tree)
+        }
+    }
+    Locals(&mut locals).visit_program(&program);
+    assert_eq!(locals, ["A", "B", "C", "D"]);
+}
@@ -0,0 +1,88 @@
+//! Helpers shared by the integration tests, each test file uses a part of
+//! them.
+#![allow(dead_code)]
+
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_devices::value::YololValue;
+use yolol_runner::ChipLimits;
+use yolol_runner::YololRunner;
+use yolol_runner::YololRunnerBuilder;
+
+/// Compile `source`, failing the test on any diagnostic.
+pub fn compile(source: &str) -> YololRunner {
+    let runner = YololRunner::from_source(source).unwrap();
+    assert!(
+        runner.diagnostics().is_empty(),
+        "{:?}",
+        runner.diagnostics()
+    );
+    runner
+}
+
+/// Get a builder of `source` without the limits of the chip.
+pub fn unlimited(source: &str) -> YololRunnerBuilder {
+    YololRunner::builder()
+        .limits(ChipLimits::GAME.unlimited())
+        .source(source)
+}
+
+/// Compile `source` and run its first line.
+pub fn run(source: &str) -> YololRunner {
+    let mut runner = compile(source);
+    runner.step();
+    runner
+}
+
+/// Get the value of the global `name`, given without its `:`.
+pub fn global_value(runner: &YololRunner, name: &str) -> YololValue {
+    let global = runner.get_global().into_iter().find(|g| g.name() == name);
+    (*global.unwrap()).clone()
+}
+
+/// Get the value of the global `name` as the game shows it.
+pub fn global(runner: &YololRunner, name: &str) -> String {
+    global_value(runner, name).to_string()
+}
+
+/// Compile `source` under `limits`, failing the test on any diagnostic, and
+/// get its globals as `name=value` after `steps` steps.
+pub fn globals(source: &str, limits: ChipLimits, steps: usize) -> Vec<String> {
+    let mut runner = YololRunner::builder()
+        .limits(limits)
+        .source(source)
+        .build()
+        .unwrap();
+    assert!(
+        runner.diagnostics().is_empty(),
+        "{:?}",
+        runner.diagnostics()
+    );
+    for _ in 0..steps {
+        runner.step();
+    }
+    runner
+        .get_global()
+        .iter()
+        .map(|g| format!("{}={}", g.name(), **g))
+        .collect()
+}
+
+/// Run the first line of `source` and check it ends without error with the
+/// `expected` values of its globals.
+pub fn check(source: &str, expected: &[(&str, &str)]) {
+    let runner = run(source);
+    assert!(runner.last_error().is_none(), "{}", source);
+    for (name, value) in expected {
+        assert_eq!(global(&runner, name), *value, "{} : {}", source, name);
+    }
+}
+
+/// Get a xorshift generator, to get the same random scripts on every run.
+pub fn xorshift(mut seed: u64) -> impl FnMut() -> u64 {
+    move || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    }
+}
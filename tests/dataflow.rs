@@ -0,0 +1,56 @@
+mod common;
+
+use common::compile;
+use common::global;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::Instruction;
+use yolol_runner::YololRunner;
+
+/// Check that the 1-indexed `line` still tests its condition.
+fn branches(runner: &YololRunner, line: usize) -> bool {
+    runner.vm().lines()[line - 1]
+        .iter()
+        .any(|i| matches!(i, Instruction::JumpFalse(_)))
+}
+
+#[test]
+fn types_flow_to_the_next_line() {
+    let runner = compile("a=\"s\"\nif a then :b=1 else :b=2 end");
+    assert!(!branches(&runner, 2));
+    let runner = compile("a=\"s\" goto 3\na=1\nif a then :b=1 end");
+    assert!(!branches(&runner, 3));
+}
+
+#[test]
+fn globals_are_never_known() {
+    let runner = compile(":a=\"s\"\nif :a then :b=1 end");
+    assert!(branches(&runner, 2));
+}
+
+#[test]
+fn computed_gotos_reach_every_line() {
+    let runner = compile("a=\"s\" goto :n\na=1\nif a then :b=1 end");
+    assert!(branches(&runner, 3));
+}
+
+#[test]
+fn last_line_wraps_to_the_first() {
+    let source = "if a then :b=1 end\na=1";
+    let mut runner = compile(source);
+    assert!(branches(&runner, 1));
+    for _ in 0..21 {
+        runner.step();
+    }
+    assert_eq!(global(&runner, "b"), "1");
+}
+
+#[test]
+fn failing_lines_stop_after_any_store() {
+    let source = "a=\"s\" :x=1/:z a=1\nif a then :b=1 else :b=2 end";
+    let mut runner = compile(source);
+    assert!(branches(&runner, 2));
+    runner.step();
+    assert!(runner.last_error().is_some());
+    runner.step();
+    assert_eq!(global(&runner, "b"), "2");
+}
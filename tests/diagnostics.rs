@@ -0,0 +1,41 @@
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::YololRunner;
+
+/// Write `source` to a file of the temporary directory, named after the test.
+fn script(name: &str, source: &str) -> String {
+    let path = std::env::temp_dir().join(format!("yolol-runner-{}.yolol", name));
+    std::fs::write(&path, source).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn reparsing_starts_over() {
+    let path = script("reparse", ":a=1 b=*2\nc=3");
+    let mut runner = YololRunner::default();
+    let first = runner.parse_with_diagnostics(&path);
+    assert_eq!(first.len(), 1);
+    let symbols = runner.symbols().len();
+
+    let second = runner.parse_with_diagnostics(&path);
+    assert_eq!(second, first);
+    assert_eq!(runner.diagnostics().len(), 1);
+    assert_eq!(runner.symbols().len(), symbols);
+
+    runner.parse(&path).unwrap();
+    assert_eq!(runner.diagnostics().len(), 1);
+    assert_eq!(runner.symbols().len(), symbols);
+}
+
+#[test]
+fn reparsing_forgets_the_old_script() {
+    let mut runner = YololRunner::default();
+    runner.parse_with_diagnostics(&script("old", "b=*2 :old=1"));
+    let diagnostics = runner.parse_with_diagnostics(&script("new", ":new=1"));
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    let globals: Vec<String> = runner
+        .get_global()
+        .iter()
+        .map(|g| g.name().to_string())
+        .collect();
+    assert_eq!(globals, ["new"]);
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::compile;
+use common::global;
+use common::run;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::RuntimeErrorKind;
+
+#[test]
+fn out_of_domain_numbers() {
+    for source in [
+        ":x=-1 :r=sqrt :x",
+        ":x=-0.001 :r=sqrt :x",
+        ":x=2 :r=asin :x",
+        ":x=-1.001 :r=asin :x",
+        ":x=5 :r=acos :x",
+        ":x=-2 :r=acos :x",
+    ] {
+        let runner = run(source);
+        let err = runner.last_error().expect(source);
+        assert_eq!(err.kind, RuntimeErrorKind::DomainError, "{}", source);
+        assert_eq!(err.line, 1);
+        // the line stops before the result is stored
+        assert_eq!(global(&runner, "r"), "0", "{}", source);
+    }
+}
+
+#[test]
+fn constant_out_of_domain() {
+    for source in [":r=sqrt -1", ":r=asin 2", ":r=acos 5"] {
+        let runner = run(source);
+        let err = runner.last_error().expect(source);
+        assert_eq!(err.kind, RuntimeErrorKind::DomainError, "{}", source);
+    }
+}
+
+#[test]
+fn domain_bounds_are_valid() {
+    for (source, expected) in [
+        (":x=0 :r=sqrt :x", "0"),
+        (":x=4 :r=sqrt :x", "2"),
+        (":x=1 :r=asin :x", "90"),
+        (":x=-1 :r=acos :x", "180"),
+        (":x=0 :r=acos :x", "90"),
+    ] {
+        let runner = run(source);
+        assert_eq!(runner.last_error(), None, "{}", source);
+        assert_eq!(global(&runner, "r"), expected, "{}", source);
+    }
+}
+
+#[test]
+fn domain_error_points_at_the_function() {
+    let source = ":x=2 :r=asin :x";
+    let mut runner = compile(&format!(":a=1\n{}", source));
+    runner.step();
+    assert!(runner.last_error().is_none());
+    runner.step();
+    let err = runner.last_error().unwrap();
+    assert_eq!(err.line, 2);
+    let span = runner.span(err.line, err.instruction).unwrap();
+    assert_eq!(&source[span.start..span.end], "asin :x");
+}
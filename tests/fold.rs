@@ -0,0 +1,98 @@
+mod common;
+
+use common::compile;
+use common::global_value;
+use common::run;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_devices::value::YololInt;
+use yolol_devices::value::YololValue;
+use yolol_runner::Instruction;
+use yolol_runner::RuntimeErrorKind;
+
+fn line(source: &str) -> Vec<String> {
+    compile(source).vm().lines()[0]
+        .iter()
+        .map(|i| format!("{:?}", i))
+        .collect()
+}
+
+fn value(source: &str) -> YololValue {
+    global_value(&run(source), "a")
+}
+
+fn int(v: i64) -> YololValue {
+    YololInt::new_raw(v).into()
+}
+
+#[test]
+fn constants_are_computed_once() {
+    assert_eq!(line(":a=1+2*3").len(), 2);
+    assert_eq!(value(":a=1+2*3"), int(7000));
+    assert_eq!(value(":a=(2^3-1)/2+abs -2.5"), int(6000));
+    assert_eq!(value(":a=\"ab\"+1-\"1\""), YololValue::from("ab"));
+    assert_eq!(value(":a=not (1<2 and 3>=3)"), int(0));
+    assert_eq!(value(":a=-(2+1)!"), int(-6000));
+}
+
+#[test]
+fn failing_operations_are_kept() {
+    for (source, kind) in [
+        (":a=1/0", RuntimeErrorKind::DivideByZero),
+        (":a=2%(1-1)", RuntimeErrorKind::DivideByZero),
+    ] {
+        let mut runner = compile(source);
+        assert_eq!(runner.run().unwrap_err().kind, kind, "{}", source);
+    }
+    let source = ":b=2 :a=3*(1/0)";
+    let mut runner = compile(source);
+    let err = runner.run().unwrap_err();
+    let span = runner.span(err.line, err.instruction).unwrap();
+    assert_eq!(&source[span.start..span.end], "(1/0)");
+}
+
+#[test]
+fn costly_or_overflowing_operations_are_kept() {
+    // the remainder overflows in yolol-devices
+    let code = line(":a=-9223372036854775.808 % -0.001");
+    assert!(code.iter().any(|i| i == "Mod"), "{:?}", code);
+    // the factorial would take ages to compute
+    let code = line(":a=9999999999!");
+    assert!(code.iter().any(|i| i == "Fac"), "{:?}", code);
+    let code = line(":a=2^1000");
+    assert!(code.iter().any(|i| i == "Exp"), "{:?}", code);
+    assert_eq!(value(":a=18!"), int(6402373705728000000));
+    assert_eq!(line(":a=18!").len(), 2);
+    assert_eq!(line(":a=2^-2+7%-2").len(), 2);
+}
+
+#[test]
+fn adding_zero_to_a_number() {
+    assert_eq!(line(":b=:a*2+0"), line(":b=:a*2"));
+    assert_eq!(line(":b=0+:a*2"), line(":b=:a*2"));
+    assert_eq!(line(":b=:a*2-(1-1)"), line(":b=:a*2"));
+    // strings append or remove the zero
+    assert_ne!(line(":b=:a+0"), line(":b=:a"));
+    assert_ne!(line(":b=:a-0"), line(":b=:a"));
+    assert_ne!(line(":b=0-:a*2"), line(":b=:a*2"));
+    // huge numbers wrap when multiplied
+    assert_ne!(line(":b=:a*2*1"), line(":b=:a*2"));
+}
+
+#[test]
+fn jumps_follow_folded_code() {
+    let source = "if :a then :b=1+1 else :b=2*3+1 end :c=4-1";
+    let runner = compile(source);
+    assert_eq!(runner.vm().lines()[0].len(), 9);
+    for (a, b) in [(1, 2000), (0, 7000)] {
+        let mut runner = compile(source);
+        runner.vm_mut().set_variable(0, int(a * 1000));
+        runner.step();
+        assert_eq!(runner.vm().variable(1), Some(&int(b)));
+        assert_eq!(runner.vm().variable(2), Some(&int(3000)));
+    }
+    // a constant condition is pruned
+    let runner = compile("if 1+1 then :b=1 end");
+    assert!(!runner.vm().lines()[0]
+        .iter()
+        .any(|i| matches!(i, Instruction::JumpFalse(_))));
+}
@@ -0,0 +1,173 @@
+mod common;
+
+use common::xorshift;
+use yolol_runner::ast::Span;
+use yolol_runner::ast::Spanned;
+use yolol_runner::ast::Tree;
+use yolol_runner::format_line;
+use yolol_runner::format_program;
+use yolol_runner::parse_program;
+use yolol_runner::Spacing;
+
+fn check(source: &str, spaced: &str, compact: &str) {
+    let program = parse_program(source);
+    assert!(program.diagnostics.is_empty(), "{:?}", program.diagnostics);
+    assert_eq!(format_program(&program, Spacing::Spaced), spaced);
+    assert_eq!(format_program(&program, Spacing::Compact), compact);
+}
+
+/// Format `line` in both styles and check the trees read back are the same.
+fn round_trip(line: &[Spanned<Tree>]) {
+    let expected: Vec<_> = line.iter().cloned().map(|t| t.without_spans()).collect();
+    for spacing in [Spacing::Spaced, Spacing::Compact] {
+        let source = format_line(line, spacing);
+        let program = parse_program(&source).without_spans();
+        assert!(program.diagnostics.is_empty(), "{} {:?}", source, line);
+        assert_eq!(program.lines[0], expected, "{}", source);
+    }
+}
+
+#[test]
+fn syntax_errors_are_kept() {
+    for (source, spaced, compact) in [
+        ("x=1 y=)+( z=3", "x = 1 y=)+( z = 3", "x=1 y=)+( z=3"),
+        (":a=\"s\" )( (1)", ":a = \"s\" )( (1)", ":a=\"s\" )( (1)"),
+        ("$ a=1 //c", "$ a = 1 //c", "$ a=1//c"),
+    ] {
+        let program = parse_program(source);
+        assert!(!program.diagnostics.is_empty(), "{}", source);
+        assert_eq!(format_program(&program, Spacing::Spaced), spaced);
+        assert_eq!(format_program(&program, Spacing::Compact), compact);
+        for formatted in [spaced, compact] {
+            let again = parse_program(formatted).without_spans();
+            assert_eq!(again.lines, program.clone().without_spans().lines);
+        }
+    }
+}
+
+#[test]
+fn canonical() {
+    check(
+        ":A=(1+2)*3 b=((4)) // note",
+        ":a = (1 + 2) * 3 b = 4 // note",
+        ":a=(1+2)*3 b=4// note",
+    );
+    check(
+        "IF :x THEN goto 2 ELSE :y+=1 END",
+        "if :x then goto 2 else :y += 1 end",
+        "if:x then goto 2 else:y+=1 end",
+    );
+    check(
+        "x = not (a and b) or - 1.50 - -2",
+        "x = not (a and b) or - 1.5 - -2",
+        "x=not(a and b)or- 1.5- -2",
+    );
+    check(
+        "x = (1 - 2) - (3 - 4)",
+        "x = 1 - 2 - (3 - 4)",
+        "x=1-2-(3-4)",
+    );
+    check("x = (2 ^ 3) ^ (a!)!", "x = 2 ^ 3 ^ a!!", "x=2^3^a!!");
+    check(
+        "x = -(a ^ 2) + (-a) ^ 2",
+        "x = -(a ^ 2) + -a ^ 2",
+        "x=-(a^2)+-a^2",
+    );
+    check(
+        "x=a++ + ++b (-c) (--d)",
+        "x = a++ + ++b (-c) (--d)",
+        "x=a++ + ++b(-c)(--d)",
+    );
+    check("x=a! == b", "x = a! == b", "x=a! ==b");
+    check(
+        "x = (not b) + 1 y = not b + 1",
+        "x = (not b) + 1 y = not b + 1",
+        "x=(not b)+1 y=not b+1",
+    );
+}
+
+#[test]
+fn parsed_lines() {
+    let program = parse_program(
+        ":a = 0.005 :b = -0.5 :c = 9223372036854775.807 :d = \"st r\"\n\
+         if not :a then :b = abs -:c else if :x then end end\n\
+         :a = :b <= :c != :d >= 1 < 2 > 3 x = (1 + 2) % 3 / 4 * 5\n\
+         :c-- --:d :e^=2 :f%=sin cos tan asin acos atan sqrt 2",
+    );
+    assert!(program.diagnostics.is_empty(), "{:?}", program.diagnostics);
+    for line in &program.lines {
+        round_trip(line);
+    }
+}
+
+fn span<T>(node: T) -> Spanned<T> {
+    Spanned::new(node, Span::default())
+}
+
+/// Build a random expression of at most `depth` levels.
+fn expr(next: &mut impl FnMut() -> u64, depth: u32) -> Spanned<Tree> {
+    if depth == 0 || next().is_multiple_of(4) {
+        return span(match next() % 5 {
+            0 => Tree::Numerical((next() % 100_000) as i64 - 50_000),
+            1 => Tree::String("s".to_string()),
+            2 => Tree::GlobalVariable("g".to_string()),
+            _ => Tree::LocalVariable("l".to_string()),
+        });
+    }
+    let kind = next() % 28;
+    let mut sub = || Box::new(expr(next, depth - 1));
+    let var = || Box::new(span(Tree::LocalVariable("v".to_string())));
+    span(match kind {
+        0 => Tree::Or(sub(), sub()),
+        1 => Tree::And(sub(), sub()),
+        2 => Tree::Eq(sub(), sub()),
+        3 => Tree::Ne(sub(), sub()),
+        4 => Tree::Lt(sub(), sub()),
+        5 => Tree::Gt(sub(), sub()),
+        6 => Tree::Lte(sub(), sub()),
+        7 => Tree::Gte(sub(), sub()),
+        8 => Tree::Add(sub(), sub()),
+        9 => Tree::Sub(sub(), sub()),
+        10 => Tree::Mul(sub(), sub()),
+        11 => Tree::Div(sub(), sub()),
+        12 => Tree::Mod(sub(), sub()),
+        13 => Tree::Exp(sub(), sub()),
+        14 => Tree::Not(sub()),
+        15 => Tree::Abs(sub()),
+        16 => Tree::Sqrt(sub()),
+        17 => Tree::Sin(sub()),
+        18 => Tree::Atan(sub()),
+        19 | 20 => Tree::Neg(sub()),
+        21 => Tree::Fac(sub()),
+        22 => Tree::PreInc(var()),
+        23 => Tree::PreDec(var()),
+        24 => Tree::PostInc(var()),
+        25 => Tree::PostDec(var()),
+        _ => Tree::Sub(sub(), Box::new(span(Tree::Neg(sub())))),
+    })
+}
+
+fn stmt(next: &mut impl FnMut() -> u64, depth: u32) -> Spanned<Tree> {
+    let var = Box::new(span(Tree::GlobalVariable("a".to_string())));
+    match next() % 6 {
+        0 => span(Tree::Assign(var, Box::new(expr(next, depth)))),
+        1 => span(Tree::AssignSub(var, Box::new(expr(next, depth)))),
+        2 => span(Tree::Goto(Box::new(expr(next, depth)))),
+        3 if depth > 0 => {
+            let p = Box::new(expr(next, depth - 1));
+            let t = (0..next() % 3).map(|_| stmt(next, depth - 1)).collect();
+            let f = (0..next() % 3).map(|_| stmt(next, depth - 1)).collect();
+            span(Tree::IfThenElse(p, t, f))
+        }
+        _ => expr(next, depth),
+    }
+}
+
+#[test]
+fn random_trees() {
+    let mut next = xorshift(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..1500 {
+        let line: Vec<_> = (0..1 + next() % 3).map(|_| stmt(&mut next, 4)).collect();
+        round_trip(&line);
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use common::check;
+use common::global;
+use common::run;
+use yolol_runner::RuntimeErrorKind;
+
+#[test]
+fn number_statements() {
+    check(":a=1 :a++", &[("a", "2")]);
+    check(":a=1 ++:a", &[("a", "2")]);
+    check(":a=1 :a--", &[("a", "0")]);
+    check(":a=1 --:a", &[("a", "0")]);
+}
+
+#[test]
+fn number_expressions() {
+    check(":a=1 :b=:a++", &[("a", "2"), ("b", "1")]);
+    check(":a=1 :b=++:a", &[("a", "2"), ("b", "2")]);
+    check(":a=1 :b=:a--", &[("a", "0"), ("b", "1")]);
+    check(":a=1 :b=--:a", &[("a", "0"), ("b", "0")]);
+    check(":a=1 :b=:a++ + :a", &[("a", "2"), ("b", "3")]);
+    check(":a=1 :b=++:a * 3", &[("a", "2"), ("b", "6")]);
+}
+
+#[test]
+fn operands_run_left_to_right() {
+    check(":a=1 :b=:a++ == :a", &[("a", "2"), ("b", "0")]);
+    check(":a=1 :b=:a != :a++", &[("a", "2"), ("b", "0")]);
+    check(":a=1 :b=:a > --:a", &[("a", "0"), ("b", "1")]);
+    check(":a=1 :b=:a < ++:a", &[("a", "2"), ("b", "1")]);
+    check(":a=1 :b=--:a or :a", &[("a", "0"), ("b", "0")]);
+    check(":a=0 :b=:a++ and :a", &[("a", "1"), ("b", "0")]);
+    check(":a=1 :b=:a++ - :a--", &[("a", "1"), ("b", "-1")]);
+    check(":a=\"x\" :b=:a++ + --:a", &[("a", "x"), ("b", "xx")]);
+}
+
+#[test]
+fn string_statements() {
+    check(":a=\"ab\" :a++", &[("a", "ab ")]);
+    check(":a=\"ab\" ++:a", &[("a", "ab ")]);
+    check(":a=\"ab\" :a--", &[("a", "a")]);
+    check(":a=\"ab\" --:a", &[("a", "a")]);
+}
+
+#[test]
+fn string_expressions() {
+    check(":a=\"ab\" :b=:a++", &[("a", "ab "), ("b", "ab")]);
+    check(":a=\"ab\" :b=++:a", &[("a", "ab "), ("b", "ab ")]);
+    check(":a=\"ab\" :b=:a--", &[("a", "a"), ("b", "ab")]);
+    check(":a=\"ab\" :b=--:a", &[("a", "a"), ("b", "a")]);
+}
+
+#[test]
+fn empty_string_decrement() {
+    for line in &[":a=\"\" :a-- :c=1", ":a=\"\" :b=--:a :c=1"] {
+        let runner = run(line);
+        let error = runner.last_error().expect(line);
+        assert_eq!(error.kind, RuntimeErrorKind::InvalidStringOp);
+        assert_eq!(error.line, 1);
+        assert_eq!(global(&runner, "c"), "0");
+    }
+}
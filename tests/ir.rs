@@ -0,0 +1,262 @@
+mod common;
+
+use common::compile;
+use yolol_devices::value::YololInt;
+use yolol_devices::value::YololValue;
+use yolol_runner::verify;
+use yolol_runner::BinOp;
+use yolol_runner::Block;
+use yolol_runner::BlockId;
+use yolol_runner::Bool;
+use yolol_runner::Edge;
+use yolol_runner::Exit;
+use yolol_runner::Inst;
+use yolol_runner::Instruction;
+use yolol_runner::IrLine;
+use yolol_runner::LowerError;
+use yolol_runner::Op;
+use yolol_runner::OptLevel;
+use yolol_runner::RuntimeError;
+use yolol_runner::Span;
+use yolol_runner::SymbolTable;
+use yolol_runner::Type;
+use yolol_runner::UnOp;
+use yolol_runner::ValueId;
+use yolol_runner::YololRunner;
+use yolol_runner::VM;
+
+fn ir(source: &str) -> IrLine {
+    compile(source).ir()[0].clone()
+}
+
+#[test]
+fn if_else_splits_the_line_in_blocks() {
+    let line = ir("if :a then :b=1 else :b=2 end :c=3");
+    assert_eq!(line.blocks.len(), 4);
+    let cond = match line.blocks[0].exit {
+        Exit::Branch {
+            cond,
+            then,
+            otherwise,
+            ..
+        } => {
+            assert_eq!((then, otherwise), (BlockId(1), BlockId(2)));
+            cond
+        }
+        exit => panic!("{:?}", exit),
+    };
+    assert_eq!(line.inst(cond).op, Op::Load(0));
+    assert!(matches!(line.blocks[1].exit, Exit::Jump(BlockId(3), _)));
+    assert!(matches!(line.blocks[2].exit, Exit::Jump(BlockId(3), _)));
+    assert_eq!(line.blocks[3].exit, Exit::End);
+    assert_eq!(line.blocks[3].insts.len(), 2);
+}
+
+#[test]
+fn values_are_defined_once_with_their_type() {
+    let line = ir(":a=\"x\"+1 :b=2*:a");
+    let types: Vec<Option<Type>> = line.insts.iter().map(|i| i.ty).collect();
+    assert_eq!(
+        types,
+        [
+            Some(Type::String),
+            Some(Type::Int(Bool::True)),
+            Some(Type::String),
+            None,
+            Some(Type::Int(Bool::True)),
+            Some(Type::Unknown),
+            Some(Type::Int(Bool::Unknown)),
+            None,
+        ]
+    );
+    assert_eq!(
+        line.inst(ValueId(2)).op,
+        Op::Binary(BinOp::Add, ValueId(0), ValueId(1))
+    );
+    assert_eq!(line.uses().iter().filter(|u| **u > 1).count(), 0);
+    assert_eq!(
+        line.to_string(),
+        "b0:\n  v0 = \"x\" : string\n  v1 = 1 : true\n  v2 = v0 + v1 : string\n  [0] = v2\n  \
+         v4 = 2 : true\n  v5 = [0] : unknown\n  v6 = v4 * v5 : number\n  [1] = v6\n  \
+         may abort on v6\n  end\n"
+    );
+}
+
+#[test]
+fn runtime_errors_are_abort_edges() {
+    let line = ir(":a=1+2 :b=3");
+    assert_eq!(line.successors(BlockId(0)), [Edge::NextLine]);
+
+    let line = ir(":a=1/:b");
+    assert_eq!(line.blocks[0].aborts, [ValueId(2)]);
+    assert_eq!(line.successors(BlockId(0)), [Edge::NextLine, Edge::Abort]);
+
+    let line = ir("goto :b");
+    assert_eq!(line.blocks[0].aborts, [ValueId(0)]);
+    assert_eq!(
+        line.successors(BlockId(0)),
+        [Edge::Goto(ValueId(0)), Edge::Abort]
+    );
+    let line = ir("goto 2 :a=1");
+    assert!(line.blocks[0].aborts.is_empty());
+    assert_eq!(line.successors(BlockId(0)), [Edge::Goto(ValueId(0))]);
+    assert_eq!(line.successors(BlockId(1)), [Edge::NextLine]);
+
+    let runner = YololRunner::from_source(":a=1 $$ :b=2").unwrap();
+    let line = &runner.ir()[0];
+    assert!(matches!(line.blocks[0].exit, Exit::Abort(_)));
+    assert_eq!(line.successors(BlockId(0)), [Edge::Abort]);
+}
+
+#[test]
+fn lowering_gives_the_bytecode_of_the_vm() {
+    for source in [
+        ":a=b++ c=--:a",
+        "x-=1 if a<b and not c then :d=1 goto 2 else if e then f++ end end g",
+        "if a then else :b=2 end",
+    ] {
+        let runner = YololRunner::builder()
+            .source(source)
+            .opt_level(OptLevel::None)
+            .build()
+            .unwrap();
+        let code: Vec<_> = runner.ir()[0]
+            .lower(&mut SymbolTable::new())
+            .unwrap()
+            .into_iter()
+            .map(|(i, _)| i)
+            .collect();
+        assert_eq!(
+            format!("{:?}", code),
+            format!("{:?}", runner.vm().lines()[0])
+        );
+    }
+}
+
+/// Lower `line`, whose variables are `a`, `b` and `r`, and run it.
+fn run_lowered(line: &IrLine, a: i64, b: i64) -> Result<YololValue, RuntimeError> {
+    let mut symbols = SymbolTable::new();
+    for name in ["a", "b", "r"] {
+        symbols.local(name);
+    }
+    let code = line.lower(&mut symbols).unwrap();
+    let code: Vec<Instruction> = code.into_iter().map(|(i, _)| i).collect();
+    verify(&code, symbols.len()).unwrap();
+    let mut vm = VM::new(vec![code], symbols.len());
+    vm.set_variable(0, a.into());
+    vm.set_variable(1, b.into());
+    vm.run()?;
+    Ok(vm.variable(2).cloned().unwrap())
+}
+
+fn inst(op: Op) -> Inst {
+    let ty = match op {
+        Op::Store(_, _) | Op::Discard(_) => None,
+        _ => Some(Type::Unknown),
+    };
+    Inst {
+        op,
+        ty,
+        span: Span::default(),
+    }
+}
+
+fn block(insts: &[usize], exit: Exit) -> Block {
+    Block {
+        insts: insts.iter().map(|v| ValueId(*v)).collect(),
+        aborts: vec![],
+        exit,
+    }
+}
+
+#[test]
+fn reused_values_are_kept_in_temporaries() {
+    // r = a * (a + b), `a` is used again below another value
+    let mut line = IrLine {
+        insts: vec![
+            inst(Op::Load(0)),
+            inst(Op::Load(1)),
+            inst(Op::Binary(BinOp::Add, ValueId(0), ValueId(1))),
+            inst(Op::Binary(BinOp::Mul, ValueId(0), ValueId(2))),
+            inst(Op::Store(2, ValueId(3))),
+        ],
+        blocks: vec![block(&[0, 1, 2, 3, 4], Exit::End)],
+    };
+    assert_eq!(run_lowered(&line, 2, 3), Ok(10.into()));
+
+    // r = (a + b) * (a + b)
+    line.insts[3].op = Op::Binary(BinOp::Mul, ValueId(2), ValueId(2));
+    assert_eq!(run_lowered(&line, 2, 3), Ok(25.into()));
+
+    // r = b - a, operands are not in the order they were computed
+    line.insts[3].op = Op::Binary(BinOp::Sub, ValueId(1), ValueId(0));
+    assert_eq!(run_lowered(&line, 2, 3), Ok(1.into()));
+
+    // r = (a + b) - 0.5 + (a + b), with a constant pushed again
+    line.insts[1].op = Op::Const(YololInt::new_raw(500).into());
+    line.insts[3].op = Op::Binary(BinOp::Sub, ValueId(0), ValueId(1));
+    line.insts
+        .push(inst(Op::Binary(BinOp::Add, ValueId(3), ValueId(1))));
+    line.insts[4].op = Op::Store(2, ValueId(5));
+    line.blocks[0] = block(&[0, 1, 2, 3, 5, 4], Exit::End);
+    assert_eq!(run_lowered(&line, 2, 3), Ok(2.into()));
+}
+
+#[test]
+fn values_are_kept_across_blocks() {
+    // v0 = a, if b then r = v0 end
+    let line = IrLine {
+        insts: vec![
+            inst(Op::Load(0)),
+            inst(Op::Load(1)),
+            inst(Op::Store(2, ValueId(0))),
+        ],
+        blocks: vec![
+            block(
+                &[0, 1],
+                Exit::Branch {
+                    cond: ValueId(1),
+                    then: BlockId(1),
+                    otherwise: BlockId(2),
+                    span: Span::default(),
+                },
+            ),
+            block(&[2], Exit::Jump(BlockId(2), Span::default())),
+            block(&[], Exit::End),
+        ],
+    };
+    assert_eq!(run_lowered(&line, 4, 1), Ok(4.into()));
+    assert_eq!(run_lowered(&line, 4, 0), Ok(0.into()));
+}
+
+#[test]
+fn lowering_needs_values_defined_before_their_use() {
+    let mut line = IrLine {
+        insts: vec![
+            inst(Op::Load(0)),
+            inst(Op::Unary(UnOp::Neg, ValueId(2))),
+            inst(Op::Load(1)),
+        ],
+        blocks: vec![block(&[0, 1, 2], Exit::End)],
+    };
+    assert_eq!(
+        line.lower(&mut SymbolTable::new()).unwrap_err(),
+        LowerError::Unscheduled(ValueId(2))
+    );
+
+    line.insts[1].op = Op::Unary(UnOp::Neg, ValueId(0));
+    line.blocks[0].exit = Exit::Jump(BlockId(0), Span::default());
+    assert_eq!(
+        line.lower(&mut SymbolTable::new()).unwrap_err(),
+        LowerError::Layout(BlockId(0))
+    );
+}
+
+#[test]
+fn constants_keep_their_decimals() {
+    let line = ir(":a=-0.001 :b=1.5 :c=-2.25 :d=9223372036854775.807");
+    let text = line.to_string();
+    for number in ["-0.001", "1.5", "-2.25", "9223372036854775.807"] {
+        assert!(text.contains(&format!("= {} :", number)), "{}", text);
+    }
+}
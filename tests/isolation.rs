@@ -0,0 +1,55 @@
+mod common;
+
+use common::compile;
+use common::global;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_devices::field::Field;
+use yolol_runner::RuntimeErrorKind;
+
+#[test]
+fn runners_have_their_own_variables() {
+    let mut first = compile("a=a+1 :out=a :x=1 goto 1");
+    let mut second = compile("b=10 a=a+5 :out=a :z=2 goto 1");
+    for _ in 0..2 {
+        first.step();
+        second.step();
+    }
+    assert_eq!(global(&first, "out"), "2");
+    assert_eq!(global(&second, "out"), "10");
+    // every runner only allocates the slots of its own script
+    assert_eq!(first.symbols().len(), 3);
+    assert_eq!(second.symbols().len(), 4);
+    assert!(first.get_global().iter().all(|g| g.name() != "z"));
+    assert!(second.get_global().iter().all(|g| g.name() != "x"));
+}
+
+#[test]
+fn globals_and_errors_do_not_leak() {
+    let mut first = compile(":a=:a+1\n:b=1/0");
+    let mut second = compile(":a=:a*2 :s=\"x\"\ngoto :s");
+
+    let mut global_a = Field::default();
+    global_a.set_name("a".to_string());
+    *global_a = 5.into();
+    first.update_globals(vec![global_a]);
+    first.step();
+    second.step();
+    assert_eq!(global(&first, "a"), "6");
+    assert_eq!(global(&second, "a"), "0");
+
+    first.step();
+    assert_eq!(
+        first.last_error().map(|e| e.kind),
+        Some(RuntimeErrorKind::DivideByZero)
+    );
+    assert!(second.last_error().is_none());
+    second.step();
+    assert_eq!(
+        second.last_error().map(|e| e.kind),
+        Some(RuntimeErrorKind::InvalidGoto)
+    );
+    assert_eq!(
+        first.last_error().map(|e| e.kind),
+        Some(RuntimeErrorKind::DivideByZero)
+    );
+}
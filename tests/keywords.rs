@@ -0,0 +1,45 @@
+mod common;
+
+use common::check;
+use yolol_runner::ast::Tree;
+use yolol_runner::parse_program;
+
+/// Get the name of the local variable assigned by the only statement of
+/// `line`.
+fn assigned(line: &str) -> String {
+    let program = parse_program(line);
+    assert!(program.diagnostics.is_empty(), "{}", line);
+    assert_eq!(program.lines[0].len(), 1, "{}", line);
+    match &program.lines[0][0].node {
+        Tree::Assign(a, _) => match &a.node {
+            Tree::LocalVariable(name) => name.clone(),
+            other => panic!("{} : {:?}", line, other),
+        },
+        other => panic!("{} : {:?}", line, other),
+    }
+}
+
+#[test]
+fn keyword_prefixes_are_identifiers() {
+    assert_eq!(assigned("iffy=1"), "iffy");
+    assert_eq!(assigned("endx=1"), "endx");
+    assert_eq!(assigned("gotoLine=1"), "gotoline");
+    assert_eq!(assigned("thenx=1"), "thenx");
+    assert_eq!(assigned("notes=1"), "notes");
+    check(
+        "iffy=1 endx=2 gotoLine=3 :a=iffy+endx+gotoLine",
+        &[("a", "6")],
+    );
+}
+
+#[test]
+fn uppercase_keywords() {
+    check("IF 1 THEN :a=1 ELSE :a=2 END", &[("a", "1")]);
+    check("If 0 Then :a=1 eLsE :a=2 End", &[("a", "2")]);
+    check(":a=1 :b=NOT :a OR :a AND 0", &[("b", "0")]);
+    check(":a=SQRT 16 :b=ABS -2", &[("a", "4"), ("b", "2")]);
+    let program = parse_program("GOTO 3\nGoTo 1");
+    assert!(program.diagnostics.is_empty());
+    assert!(matches!(program.lines[0][0].node, Tree::Goto(_)));
+    assert!(matches!(program.lines[1][0].node, Tree::Goto(_)));
+}
@@ -0,0 +1,106 @@
+mod common;
+
+use common::unlimited;
+use common::xorshift;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::check_levels;
+use yolol_runner::OptLevel;
+use yolol_runner::Passes;
+use yolol_runner::RuntimeErrorKind;
+
+#[test]
+fn no_optimization_runs_lines_as_written() {
+    let source = ":a=\"x\" :b=:a*(1+2) :c=1";
+    let mut runner = unlimited(source).opt_level(OptLevel::None).build().unwrap();
+    assert_eq!(runner.vm().lines()[0].len(), 10);
+    assert_eq!(
+        runner.run().unwrap_err().kind,
+        RuntimeErrorKind::InvalidStringOp
+    );
+
+    let runner = unlimited(source).build().unwrap();
+    assert_eq!(runner.passes(), OptLevel::Aggressive.passes());
+    assert!(runner.vm().lines()[0].len() < 10);
+}
+
+#[test]
+fn passes_are_toggled_one_by_one() {
+    let source = ":a=1+2 if 0 then :b=1 end";
+    let only_fold = Passes {
+        fold: true,
+        ..OptLevel::None.passes()
+    };
+    let runner = unlimited(source).passes(only_fold).build().unwrap();
+    assert_eq!(runner.vm().lines()[0].len(), 6);
+
+    let mut runner = unlimited(source).build().unwrap();
+    assert_eq!(runner.vm().lines()[0].len(), 2);
+    runner.set_opt_level(OptLevel::None);
+    assert_eq!(runner.passes(), OptLevel::None.passes());
+}
+
+#[test]
+fn scripts_behave_the_same_at_every_level() {
+    for source in [
+        "a=\"s\"\nif a then :b=1 else :b=2 end",
+        "a=\"s\" :x=1/:z a=1\nif a then :b=1 else :b=2 end goto 1",
+        "if a then :b=:b+1 end\na=1\n:c=a*2+0 :d=0+:c :e=:d-0",
+        ":s=\"ab\" :s-- :t=:s+1+2 :u=:t-\"1\"\ngoto :n\n:n=1\n:m=\"x\"*2 :k=3",
+        "i++ :f=i! :g=sqrt i :h=not i :o=i%3 :p=i^2\ngoto 1+(i<5)",
+        "if :a then :b=\"x\"*2 end :c=1",
+    ] {
+        check_levels(&unlimited(source), 50).unwrap();
+    }
+}
+
+#[test]
+fn runtime_errors_are_the_same_at_every_level() {
+    let source = ":x=\"a\" :y=:x*1 :z=1";
+    let errors: Vec<_> = OptLevel::ALL
+        .iter()
+        .map(|level| {
+            let mut runner = unlimited(source).opt_level(*level).build().unwrap();
+            runner.step();
+            let err = runner.last_error().cloned().unwrap();
+            let span = runner.span(err.line, err.instruction).unwrap();
+            assert_eq!(&source[span.start..span.end], ":x*1", "{}", level.name());
+            (err.kind, err.line, span)
+        })
+        .collect();
+    assert_eq!(errors[0].0, RuntimeErrorKind::InvalidStringOp);
+    assert!(errors.iter().all(|e| *e == errors[0]));
+
+    for source in [
+        source,
+        ":x=\"a\" :y=-:x :z=1",
+        "a=\"s\" b=a/2 :c=1\n:d=a",
+        ":a=1 b=\"s\" :c=sqrt b goto 1",
+    ] {
+        check_levels(&unlimited(source), 10).unwrap();
+    }
+}
+
+#[test]
+fn random_scripts_behave_the_same_at_every_level() {
+    const TOKENS: [&str; 34] = [
+        " ", ":a", "b", "1", "0", "0.5", "-", "+", "*", "/", "%", "^", "!", "(", ")", "=", "+=",
+        "++", "--", "==", "<", "and", "or", "not", "sqrt", "abs", "if", "then", "else", "end",
+        "goto", "\"s\"", "\"\"", ":c",
+    ];
+    let mut next = xorshift(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..1000 {
+        let lines = 1 + next() % 4;
+        let source: Vec<String> = (0..lines)
+            .map(|_| {
+                let len = next() % 16;
+                (0..len)
+                    .map(|_| TOKENS[(next() % TOKENS.len() as u64) as usize])
+                    .collect()
+            })
+            .collect();
+        let source = source.join("\n");
+        if let Err(err) = check_levels(&unlimited(&source), 12) {
+            panic!("{:?}: {}", source, err);
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use yolol_runner::ChipKind;
+use yolol_runner::ChipLimits;
+use yolol_runner::LimitMode;
+use yolol_runner::YololRunner;
+
+const TIERS: [ChipKind; 3] = [ChipKind::Basic, ChipKind::Advanced, ChipKind::Professional];
+
+#[test]
+fn every_tier_has_the_game_limits() {
+    assert_eq!(ChipKind::Basic.limits(), ChipLimits::BASIC);
+    assert_eq!(ChipKind::Advanced.limits(), ChipLimits::ADVANCED);
+    assert_eq!(ChipKind::Professional.limits(), ChipLimits::PROFESSIONAL);
+    for chip in TIERS {
+        let limits = chip.limits();
+        assert_eq!(limits.max_lines, 20, "{}", chip.name());
+        assert_eq!(limits.max_line_length, 70, "{}", chip.name());
+        assert_eq!(limits.mode, LimitMode::Lenient, "{}", chip.name());
+    }
+}
+
+#[test]
+fn runner_checks_the_limits_of_its_chip() {
+    let long = format!(":a=\"{}\"", "x".repeat(70));
+    for chip in TIERS {
+        let runner = YololRunner::builder()
+            .chip(chip)
+            .line(&long)
+            .build()
+            .unwrap();
+        assert_eq!(runner.limits(), chip.limits(), "{}", chip.name());
+        assert_eq!(runner.diagnostics().len(), 1, "{}", chip.name());
+        assert_eq!(runner.vm().lines().len(), chip.limits().max_lines);
+    }
+}
+
+#[test]
+fn given_limits_replace_the_ones_of_the_chip() {
+    let limits = ChipLimits {
+        max_lines: 3,
+        max_line_length: 10,
+        mode: LimitMode::Strict,
+    };
+    for chip in TIERS {
+        let mut runner = YololRunner::builder()
+            .chip(chip)
+            .limits(limits)
+            .lines([":a=1", ":b=2", ":c=3", ":d=4"])
+            .build()
+            .unwrap();
+        assert_eq!(runner.limits(), limits);
+        assert_eq!(runner.vm().lines().len(), 3);
+        assert_eq!(runner.diagnostics().len(), 1);
+        assert!(runner.diagnostics()[0]
+            .to_string()
+            .contains("chip allows 3"));
+
+        runner.set_chip(ChipKind::Basic);
+        assert_eq!(runner.limits(), limits);
+    }
+}
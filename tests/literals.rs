@@ -0,0 +1,64 @@
+mod common;
+
+use common::global_value;
+use common::run;
+use yolol_devices::value::YololInt;
+use yolol_devices::value::YololValue;
+
+fn eval(literal: &str) -> YololValue {
+    global_value(&run(&format!(":r={}", literal)), "r")
+}
+
+fn check(cases: &[(&str, i64)]) {
+    for (literal, expected) in cases {
+        let expected: YololValue = YololInt::new_raw(*expected).into();
+        assert_eq!(eval(literal), expected, "{}", literal);
+    }
+}
+
+#[test]
+fn decimals() {
+    check(&[
+        ("0", 0),
+        ("1", 1000),
+        ("00012", 12000),
+        (".5", 500),
+        ("0.5", 500),
+        ("1.05", 1050),
+        ("0.001", 1),
+        ("1.2345", 1234),
+        ("1.2399999", 1239),
+    ]);
+}
+
+#[test]
+fn negative() {
+    check(&[
+        ("-1", -1000),
+        ("-0.5", -500),
+        ("-.5", -500),
+        ("-1.2345", -1234),
+        ("-0.001", -1),
+    ]);
+}
+
+#[test]
+fn precision() {
+    check(&[
+        ("9007199254740993", 9_007_199_254_740_993_000),
+        ("123456789012.345", 123_456_789_012_345),
+        ("-4503599627370497.001", -4_503_599_627_370_497_001),
+    ]);
+}
+
+#[test]
+fn bounds() {
+    check(&[
+        ("9223372036854775.807", i64::MAX),
+        ("-9223372036854775.808", i64::MIN),
+        ("9223372036854775.808", i64::MAX),
+        ("9223372036854775807", i64::MAX),
+        ("-99999999999999999999999", i64::MIN),
+        ("100000000000000000000000.5", i64::MAX),
+    ]);
+}
@@ -0,0 +1,65 @@
+mod common;
+
+use common::globals;
+use yolol_runner::minify;
+use yolol_runner::ChipLimits;
+use yolol_runner::DiagnosticKind;
+
+#[test]
+fn strips_and_renames() {
+    let minified = minify(
+        "counter = 0.50 // start\n\
+         counter = counter + 1   :Out = ( counter * 2 ) + total\n\
+         // only a comment\n\
+         if counter > 3 then total = total + counter end goto 2",
+    );
+    assert_eq!(
+        minified.source,
+        "a=.5\na=a+1:out=a*2+b\n\nif a>3 then b=b+a end goto 2"
+    );
+    assert!(minified.diagnostics.is_empty());
+}
+
+#[test]
+fn skips_keywords() {
+    let source: Vec<String> = (0..30).map(|i| format!("v{}={}", i, i)).collect();
+    let minified = minify(&source.join(" "));
+    assert!(!minified.source.contains("if="), "{}", minified.source);
+    assert!(!minified.source.contains("or="), "{}", minified.source);
+    assert!(minified.source.contains("aa=26"), "{}", minified.source);
+}
+
+#[test]
+fn reports_long_lines() {
+    let long = format!(":a=\"{}\"", "x".repeat(80));
+    let minified = minify(&format!("a = 1\n{}\n:b=)", long));
+    assert_eq!(minified.long_lines(), [2]);
+    assert_eq!(
+        minified.diagnostics.iter().filter(|d| d.is_error()).count(),
+        1
+    );
+    assert!(minified.source.ends_with("\n:b=)"));
+}
+
+#[test]
+fn long_scripts_are_not_long_lines() {
+    let source: Vec<String> = (0..25).map(|i| format!(":a={}", i)).collect();
+    let minified = minify(&source.join("\n"));
+    assert!(minified.long_lines().is_empty());
+    assert_eq!(minified.diagnostics.len(), 1);
+    assert_eq!(minified.diagnostics[0].kind, DiagnosticKind::ScriptLength);
+}
+
+#[test]
+fn same_behaviour() {
+    let source = "i = 0 total = 0 :done = 0\n\
+                  total += i * 2 // accumulate\n\
+                  i++ if i < 5 then goto 2 end :res = total :done = 1 goto 4\n\
+                  :res = ( total - 0.500 ) / 2";
+    let minified = minify(source);
+    assert!(minified.source.len() < source.len());
+    assert_eq!(
+        globals(source, ChipLimits::GAME, 40),
+        globals(&minified.source, ChipLimits::GAME, 40)
+    );
+}
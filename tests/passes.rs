@@ -0,0 +1,101 @@
+mod common;
+
+use common::compile;
+use yolol_runner::OptLevel;
+use yolol_runner::Passes;
+use yolol_runner::YololRunner;
+
+fn reports(source: &str) -> Vec<String> {
+    compile(source)
+        .reports()
+        .iter()
+        .map(|report| report.to_string())
+        .collect()
+}
+
+#[test]
+fn every_enabled_pass_is_reported_in_order() {
+    let runner = YololRunner::from_source(":a=1\n:b=2").unwrap();
+    assert_eq!(runner.reports().len(), runner.vm().lines().len());
+    let names: Vec<&str> = runner.reports()[1].passes.iter().map(|p| p.name).collect();
+    assert_eq!(
+        names,
+        ["fold", "dead-branches", "failing-tail", "stack-cleanup"]
+    );
+    assert_eq!(runner.reports()[1].line, 2);
+    assert_eq!(runner.reports()[1].source, ":b=2");
+
+    let runner = YololRunner::builder()
+        .source(":a=1")
+        .passes(Passes {
+            dead_branches: false,
+            ..OptLevel::Aggressive.passes()
+        })
+        .build()
+        .unwrap();
+    let names: Vec<&str> = runner.reports()[0].passes.iter().map(|p| p.name).collect();
+    assert_eq!(names, ["fold", "failing-tail", "stack-cleanup"]);
+
+    let runner = YololRunner::builder()
+        .source(":a=1")
+        .opt_level(OptLevel::None)
+        .build()
+        .unwrap();
+    assert!(runner.reports()[0].passes.is_empty());
+}
+
+#[test]
+fn passes_tell_why_code_changed() {
+    let report = &reports(":a=1+2*3")[0];
+    assert!(report.contains("`1+2*3` is computed once"), "{}", report);
+    assert!(report.contains("dead-branches: unchanged"), "{}", report);
+
+    let report = &reports("if 0 then :a=1 else :b=2 end")[0];
+    assert!(
+        report.contains("condition of `0` is always false"),
+        "{}",
+        report
+    );
+    assert!(report.contains("`:a=1` never runs"), "{}", report);
+
+    let report = &reports(":a=\"x\"*2 :b=1")[0];
+    assert!(report.contains("the line stops there"), "{}", report);
+    assert!(report.contains(":b=1"), "{}", report);
+}
+
+#[test]
+fn types_from_previous_lines_explain_removals() {
+    let report = &reports("a=\"s\"\nif a then :b=1 else :b=2 end")[1];
+    assert!(
+        report.contains("condition of `a` is always false"),
+        "{}",
+        report
+    );
+
+    let runner = YololRunner::builder()
+        .source("a=\"s\"\nif a then :b=1 else :b=2 end")
+        .opt_level(OptLevel::Basic)
+        .build()
+        .unwrap();
+    let dead = &runner.reports()[1].passes[1];
+    assert_eq!(dead.name, "dead-branches");
+    assert!(!dead.changed());
+}
+
+#[test]
+fn reports_keep_the_instructions_around_each_pass() {
+    let runner = YololRunner::from_source(":a=1+2").unwrap();
+    let fold = &runner.reports()[0].passes[0];
+    assert!(fold.changed());
+    assert_eq!(fold.before.len(), 4);
+    assert_eq!(fold.after.len(), 2);
+    assert!(fold.error.is_none());
+    assert_eq!(
+        format!("{:?}", runner.reports()[0].passes[1].before),
+        format!("{:?}", fold.after)
+    );
+    assert_eq!(
+        format!("{:?}", runner.vm().lines()[0]),
+        format!("{:?}", runner.reports()[0].passes[3].after)
+    );
+}
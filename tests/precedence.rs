@@ -0,0 +1,96 @@
+mod common;
+
+use common::global;
+use common::run;
+
+fn eval(expr: &str) -> String {
+    global(&run(&format!(":r={}", expr)), "r")
+}
+
+fn check(cases: &[(&str, &str)]) {
+    for (expr, expected) in cases {
+        assert_eq!(eval(expr), *expected, "{}", expr);
+    }
+}
+
+#[test]
+fn arithmetic() {
+    check(&[
+        ("1 + 2 * 3", "7"),
+        ("(1 + 2) * 3", "9"),
+        ("10 - 4 - 3", "3"),
+        ("12 / 3 / 2", "2"),
+        ("-7 % 4 * 2", "-6"),
+        ("2 * 3 ^ 2", "18"),
+        ("2 ^ 3 ^ 2", "64"),
+        ("-2 ^ 2", "4"),
+        ("3! + 1", "7"),
+        ("2 + 3!", "8"),
+        ("2 * 3!", "12"),
+    ]);
+}
+
+#[test]
+fn keyword_operators() {
+    check(&[
+        ("sqrt 16 + 1", "5"),
+        ("abs -3 * 2", "6"),
+        ("not 0 + 1", "0"),
+        ("not 3 - 3", "1"),
+        ("not 1 == 2", "1"),
+        ("not 0 and 0", "0"),
+        ("1 and not 0 or 0", "1"),
+        ("(not 0) + 1", "2"),
+        ("not not 5", "1"),
+        ("- -2", "2"),
+    ]);
+}
+
+#[test]
+fn comparisons() {
+    check(&[
+        ("2 == 1 + 1", "1"),
+        ("1 + 1 == 2", "1"),
+        ("1 + 2 < 4", "1"),
+        ("5 - 3 != 2", "0"),
+        ("2 <= 2", "1"),
+        ("2 >= 3", "0"),
+        ("1 < 2 == 1", "1"),
+        ("3 > 2 > 1", "0"),
+        ("2 * 2 > 3", "1"),
+    ]);
+}
+
+#[test]
+fn logic() {
+    check(&[
+        ("1 or 0 and 0", "1"),
+        ("0 and 0 or 1", "1"),
+        ("0 and 1 or 0", "0"),
+        ("1 != 2 and 2 != 2 or 3 == 3", "1"),
+        ("1 == 1 and 2 == 2", "1"),
+        ("0 or 1 + 1 == 2", "1"),
+    ]);
+}
+
+#[test]
+fn strings() {
+    check(&[
+        ("\"a\" + 1 + 2", "a12"),
+        ("1 + 2 + \"a\"", "3a"),
+        ("\"ab\" == \"a\" + \"b\"", "1"),
+    ]);
+}
+
+#[test]
+fn compound_assignment() {
+    for (source, expected) in [
+        (":r=10 :r-=3", "7"),
+        (":r=12 :r/=3", "4"),
+        (":r=2 :r^=3", "8"),
+        (":r=\"ab\" :r+=\"c\"", "abc"),
+        (":r=\"abc\" :r-=\"c\"", "ab"),
+    ] {
+        assert_eq!(global(&run(source), "r"), expected, "{}", source);
+    }
+}
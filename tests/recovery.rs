@@ -0,0 +1,94 @@
+mod common;
+
+use common::global;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::ast::Span;
+use yolol_runner::ast::Spanned;
+use yolol_runner::ast::Tree;
+use yolol_runner::parse_program;
+use yolol_runner::RuntimeErrorKind;
+use yolol_runner::YololRunner;
+
+#[test]
+fn keeps_valid_statements() {
+    let program = parse_program(":a=1 :b=*2 :c=3").without_spans();
+    let line = &program.lines[0];
+    assert_eq!(line.len(), 3);
+    assert!(matches!(line[0].node, Tree::Assign(_, _)));
+    assert_eq!(
+        line[1],
+        Spanned::new(Tree::Error(":b=*2".to_string()), Span::default())
+    );
+    assert!(matches!(line[2].node, Tree::Assign(_, _)));
+    assert_eq!(program.diagnostics.len(), 1);
+}
+
+#[test]
+fn error_spans() {
+    let program = parse_program(":a=1 )( $ :b=2 @");
+    let errors: Vec<Span> = program.lines[0]
+        .iter()
+        .filter(|s| matches!(s.node, Tree::Error(_)))
+        .map(|s| s.span)
+        .collect();
+    assert_eq!(errors, [Span::new(5, 9), Span::new(15, 16)]);
+    assert_eq!(program.diagnostics.len(), 2);
+    assert_eq!(program.diagnostics[1].column, 16);
+}
+
+#[test]
+fn broken_blocks_are_one_error() {
+    let source = "if :a then :b=1 :c=*2 end :d=4";
+    let program = parse_program(source);
+    let line = &program.lines[0];
+    assert_eq!(line.len(), 2);
+    assert_eq!(
+        line[0],
+        Spanned::new(
+            Tree::Error("if :a then :b=1 :c=*2 end".to_string()),
+            Span::new(0, 25)
+        )
+    );
+    assert!(matches!(line[1].node, Tree::Assign(_, _)));
+    assert_eq!(program.diagnostics.len(), 1);
+    assert_eq!(program.diagnostics[0].column, 1);
+
+    let program = parse_program("if :a then :b=) end :c=1");
+    let line = &program.lines[0];
+    assert_eq!(
+        line[0],
+        Spanned::new(
+            Tree::Error("if :a then :b=) end".to_string()),
+            Span::new(0, 19)
+        )
+    );
+    assert!(matches!(line[1].node, Tree::Assign(_, _)));
+}
+
+#[test]
+fn recovers_at_statement_starts() {
+    let program = parse_program(":a=* b 2 :b=1 :c=) goto :d=*").without_spans();
+    let errors: Vec<&Tree> = program.lines[0].iter().map(|s| &s.node).collect();
+    assert_eq!(errors.len(), 4, "{:?}", errors);
+    assert_eq!(*errors[0], Tree::Error(":a=* b 2".to_string()));
+    assert_eq!(*errors[2], Tree::Error(":c=)".to_string()));
+    assert_eq!(*errors[3], Tree::Error("goto :d=*".to_string()));
+    assert_eq!(program.diagnostics.len(), 3);
+}
+
+#[test]
+fn runs_up_to_the_error() {
+    let mut runner = YololRunner::from_source(":a=1 :b=) :c=3\n:d=4").unwrap();
+    assert!(runner.diagnostics().iter().any(|d| d.is_error()));
+    let err = runner.run().unwrap_err();
+    assert_eq!(err.kind, RuntimeErrorKind::SyntaxError);
+    assert_eq!(
+        runner.span(err.line, err.instruction),
+        Some(Span::new(5, 9))
+    );
+    runner.step();
+    runner.step();
+    assert_eq!(global(&runner, "a"), "1");
+    assert_eq!(global(&runner, "c"), "0");
+    assert_eq!(global(&runner, "d"), "4");
+}
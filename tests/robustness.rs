@@ -0,0 +1,91 @@
+mod common;
+
+use common::global;
+use common::unlimited;
+use common::xorshift;
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::YololRunner;
+
+fn run(source: &str) -> YololRunner {
+    let mut runner = unlimited(source).build().unwrap();
+    for _ in 0..20 {
+        runner.step();
+    }
+    runner
+}
+
+#[test]
+fn malformed_scripts() {
+    let deep = format!(":a={}1{}", "(".repeat(5000), ")".repeat(5000));
+    let neg = format!(":a={}1", "- ".repeat(5000));
+    let ifs = format!("{}:a=1{}", "if 1 then ".repeat(500), " end".repeat(500));
+    for source in [
+        ":a=99999999999999999999999999999999999999",
+        ":a=-99999999999999999999999999999999999.999999",
+        ":a=.",
+        ":a=1.",
+        "++1",
+        "1++",
+        ":a+=",
+        "if then end",
+        "goto",
+        "goto \"a\"",
+        ":a=\"",
+        "\"\"--",
+        ":a=-\"b\"",
+        ":a=1/0 :b=2",
+        "é:a=1",
+        "\u{0}",
+        &deep,
+        &neg,
+        &ifs,
+    ] {
+        run(source);
+    }
+}
+
+#[test]
+fn nesting_is_reported() {
+    let source = format!(":a={}1{}", "(".repeat(500), ")".repeat(500));
+    let runner = run(&source);
+    assert!(runner.diagnostics().iter().any(|d| d.is_error()));
+}
+
+#[test]
+fn flat_chains_are_not_nested() {
+    let source = format!(":a=1{} :b=:a-1-(2-1)-x!-1", "-1".repeat(300));
+    let runner = run(&source);
+    assert!(
+        runner.diagnostics().is_empty(),
+        "{:?}",
+        runner.diagnostics()
+    );
+    assert_eq!(global(&runner, "a"), "-299");
+    assert_eq!(global(&runner, "b"), "-303");
+}
+
+#[test]
+fn long_chains_are_reported() {
+    for terms in [3000, 200000] {
+        let source = format!(":a=1{}", "+1".repeat(terms));
+        let runner = run(&source);
+        assert!(runner.diagnostics().iter().any(|d| d.is_error()));
+    }
+}
+
+#[test]
+fn random_scripts() {
+    const TOKENS: [&str; 32] = [
+        " ", ":a", "b", "1", "0.5", "-", "+", "*", "/", "%", "^", "!", "(", ")", "=", "+=", "++",
+        "--", "==", "<", "and", "or", "not", "sqrt", "sin", "if", "then", "else", "end", "goto",
+        "\"s\"", "//",
+    ];
+    let mut next = xorshift(0x2545_f491_4f6c_dd1d);
+    for _ in 0..2000 {
+        let len = next() % 24;
+        let line: String = (0..len)
+            .map(|_| TOKENS[(next() % TOKENS.len() as u64) as usize])
+            .collect();
+        run(&line);
+    }
+}
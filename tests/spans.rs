@@ -0,0 +1,44 @@
+use yolol_runner::ChipKind;
+use yolol_runner::Span;
+use yolol_runner::YololRunner;
+
+#[test]
+fn source_map_covers_every_instruction() {
+    let runner =
+        YololRunner::from_source(":a=1 if :a then :b=2 else :b=3 end\n:c++ goto 1").unwrap();
+    for (line, spans) in runner.vm().lines().iter().zip(runner.source_map()) {
+        assert_eq!(line.len(), spans.len());
+    }
+}
+
+#[test]
+fn runtime_error_points_at_operator() {
+    let source = ":a=1 :b=(:a+1)/0";
+    let mut runner = YololRunner::from_source(source).unwrap();
+    let err = runner.run().unwrap_err();
+    let span = runner.span(err.line, err.instruction).unwrap();
+    assert_eq!(&source[span.start..span.end], "(:a+1)/0");
+    assert_eq!(runner.span(2, 0), None);
+}
+
+#[test]
+fn operands_keep_their_spans() {
+    let source = "x=abc+\"str\"";
+    let runner = YololRunner::from_source(source).unwrap();
+    let spans: Vec<&str> = runner.source_map()[0]
+        .iter()
+        .map(|s| &source[s.start..s.end])
+        .collect();
+    assert_eq!(spans, ["abc", "\"str\"", "abc+\"str\"", source]);
+}
+
+#[test]
+fn diagnostics_have_columns() {
+    let runner = YololRunner::builder()
+        .chip(ChipKind::Basic)
+        .line(":a=1 :b=:a^2")
+        .build()
+        .unwrap();
+    assert_eq!(runner.diagnostics()[0].column, 9);
+    assert_eq!(Span::new(2, 4).column("é:ab"), 2);
+}
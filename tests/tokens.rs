@@ -0,0 +1,99 @@
+use yolol_runner::tokenize;
+use yolol_runner::TokenKind;
+use yolol_runner::TokenKind::*;
+
+fn kinds(line: &str) -> Vec<(TokenKind, &str)> {
+    tokenize(line)
+        .iter()
+        .map(|t| (t.kind, t.text(line)))
+        .collect()
+}
+
+#[test]
+fn token_kinds() {
+    assert_eq!(
+        kinds("if :A>=1.5 then Abc_2+=\"x y\" end // done"),
+        [
+            (Keyword, "if"),
+            (Global, ":A"),
+            (Operator, ">="),
+            (Number, "1.5"),
+            (Keyword, "then"),
+            (Local, "Abc_2"),
+            (Operator, "+="),
+            (String, "\"x y\""),
+            (Keyword, "end"),
+            (Comment, "// done"),
+        ]
+    );
+    assert_eq!(
+        kinds("a=-.5 b=SQRT(a)! c++"),
+        [
+            (Local, "a"),
+            (Operator, "="),
+            (Operator, "-"),
+            (Number, ".5"),
+            (Local, "b"),
+            (Operator, "="),
+            (Keyword, "SQRT"),
+            (Operator, "("),
+            (Local, "a"),
+            (Operator, ")"),
+            (Operator, "!"),
+            (Local, "c"),
+            (Operator, "++"),
+        ]
+    );
+}
+
+#[test]
+fn keywords_need_a_word_boundary() {
+    assert_eq!(kinds("ifa"), [(Local, "ifa")]);
+    assert_eq!(kinds("if:a"), [(Keyword, "if"), (Global, ":a")]);
+    assert_eq!(kinds("not1"), [(Local, "not1")]);
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(kinds("12.250"), [(Number, "12.250")]);
+    assert_eq!(kinds("1."), [(Number, "1"), (Error, ".")]);
+    assert_eq!(kinds("1.2.3"), [(Number, "1.2"), (Number, ".3")]);
+}
+
+#[test]
+fn broken_lines_still_tokenize() {
+    assert_eq!(
+        kinds(":a=1 $# :b=\"open"),
+        [
+            (Global, ":a"),
+            (Operator, "="),
+            (Number, "1"),
+            (Error, "$#"),
+            (Global, ":b"),
+            (Operator, "="),
+            (Error, "\"open"),
+        ]
+    );
+    assert_eq!(kinds("a=é"), [(Local, "a"), (Operator, "="), (Error, "é")]);
+    assert!(tokenize("").is_empty());
+    assert!(tokenize(" \t ").is_empty());
+}
+
+#[test]
+fn tokens_cover_everything_but_whitespace() {
+    for line in [
+        ":a=1 if :a then :b=2 else :b=3 end goto 1",
+        "x=(:a+1)/0 // comment",
+        "  what ?? \"a b\" ~~ 1.5.5",
+    ] {
+        let mut pos = 0;
+        for token in tokenize(line) {
+            assert!(line[pos..token.span.start]
+                .chars()
+                .all(|c| c == ' ' || c == '\t'));
+            assert!(token.span.start < token.span.end);
+            pos = token.span.end;
+        }
+        assert!(line[pos..].trim().is_empty());
+    }
+}
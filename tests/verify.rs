@@ -0,0 +1,116 @@
+use yolol_devices::devices::chip::CodeRunner;
+use yolol_runner::verify;
+use yolol_runner::Instruction;
+use yolol_runner::VerifyErrorKind;
+use yolol_runner::YololRunner;
+use yolol_runner::VM;
+
+fn kind(line: &[Instruction], memory: usize) -> Option<VerifyErrorKind> {
+    verify(line, memory).err().map(|e| e.kind)
+}
+
+#[test]
+fn valid_if_else() {
+    let line = [
+        Instruction::Push(0),
+        Instruction::JumpFalse(3),
+        Instruction::PushValue(1.into()),
+        Instruction::Store(0),
+        Instruction::Jump(2),
+        Instruction::PushValue(2.into()),
+        Instruction::Store(0),
+    ];
+    assert_eq!(kind(&line, 1), None);
+}
+
+#[test]
+fn jump_out_of_range() {
+    let line = [Instruction::Jump(2), Instruction::Pop];
+    assert_eq!(kind(&line, 0), Some(VerifyErrorKind::JumpOutOfRange(3)));
+}
+
+#[test]
+fn jump_past_the_largest_target() {
+    for line in [
+        [Instruction::Jump(usize::MAX), Instruction::Pop],
+        [
+            Instruction::PushValue(0.into()),
+            Instruction::JumpFalse(usize::MAX),
+        ],
+    ] {
+        assert_eq!(
+            kind(&line, 0),
+            Some(VerifyErrorKind::JumpOutOfRange(usize::MAX))
+        );
+    }
+}
+
+#[test]
+fn unverified_jumps_end_the_line() {
+    let mut vm = VM::new(
+        vec![vec![
+            Instruction::PushValue(0.into()),
+            Instruction::JumpFalse(usize::MAX),
+            Instruction::Jump(usize::MAX),
+        ]],
+        0,
+    );
+    assert_eq!(vm.run(), Ok(None));
+    let mut vm = VM::new(vec![vec![Instruction::Jump(usize::MAX)]], 0);
+    assert_eq!(vm.run(), Ok(None));
+}
+
+#[test]
+fn stack_underflow() {
+    let line = [Instruction::PushValue(1.into()), Instruction::Add];
+    assert_eq!(kind(&line, 0), Some(VerifyErrorKind::StackUnderflow));
+}
+
+#[test]
+fn stack_mismatch() {
+    let line = [
+        Instruction::Push(0),
+        Instruction::JumpFalse(1),
+        Instruction::PushValue(1.into()),
+        Instruction::PushValue(2.into()),
+    ];
+    assert!(matches!(
+        kind(&line, 1),
+        Some(VerifyErrorKind::StackMismatch { .. })
+    ));
+}
+
+#[test]
+fn unallocated_adress() {
+    let line = [Instruction::PushValue(1.into()), Instruction::Store(3)];
+    assert_eq!(kind(&line, 2), Some(VerifyErrorKind::UnallocatedAdress(3)));
+}
+
+#[test]
+fn nested_if_else() {
+    let source = ":a=1 if :x then :b=1 else if :y then :b=2 else :b=3 end :c=4 end :d=5";
+    for (x, y, b, c) in &[
+        ("1", "0", "1", "0"),
+        ("0", "1", "2", "4"),
+        ("0", "0", "3", "4"),
+    ] {
+        let source = format!(":x={} :y={}\n{}", x, y, source);
+        let mut runner = YololRunner::from_source(&source).unwrap();
+        assert!(
+            runner.diagnostics().is_empty(),
+            "{:?}",
+            runner.diagnostics()
+        );
+        runner.step();
+        runner.step();
+        let globals = runner.get_global();
+        let get = |name: &str| {
+            let global = globals.iter().find(|g| g.name() == name);
+            global.map_or("0".to_string(), |g| format!("{}", **g))
+        };
+        assert_eq!(get("a"), "1");
+        assert_eq!(get("b"), *b);
+        assert_eq!(get("c"), *c);
+        assert_eq!(get("d"), "5");
+    }
+}
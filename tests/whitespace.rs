@@ -0,0 +1,73 @@
+mod common;
+
+use common::globals;
+use yolol_runner::parse_program;
+use yolol_runner::ChipLimits;
+
+fn parses(line: &str) -> bool {
+    parse_program(line).diagnostics.is_empty()
+}
+
+#[test]
+fn spaces_and_tabs_separate_tokens() {
+    assert_eq!(
+        globals("\t:a = 1\t:b\t=\t2 \t ", ChipLimits::GAME.unlimited(), 1),
+        ["a=1", "b=2"]
+    );
+    assert_eq!(
+        globals(
+            "if\t:a\tthen :b=1\telse\t:b=2\tend",
+            ChipLimits::GAME.unlimited(),
+            1
+        ),
+        ["a=0", "b=2"]
+    );
+    assert_eq!(
+        globals(":a=1  \t  +  \t 2", ChipLimits::GAME.unlimited(), 1),
+        ["a=3"]
+    );
+}
+
+#[test]
+fn other_whitespace_is_significant() {
+    assert!(!parses(":a=1\u{a0}:b=2"));
+    assert!(!parses(":a=1\u{b}:b=2"));
+    assert!(!parses(":a=1\u{c}:b=2"));
+    assert!(!parses(":a=1\u{3000}:b=2"));
+}
+
+#[test]
+fn whitespace_splits_operators() {
+    assert!(parses(":a+=1"));
+    assert!(!parses(":a+ =1"));
+    assert!(!parses(":a=1 = =1"));
+    assert!(!parses(":a=1 < = 1"));
+    assert!(parses(":a=\"  \t \""));
+}
+
+#[test]
+fn line_breaks() {
+    for source in [
+        ":a=1\n:b=2\n:c=3",
+        ":a=1\r\n:b=2\r\n:c=3",
+        ":a=1\r:b=2\r:c=3",
+        ":a=1\r\n:b=2\r:c=3\n",
+        "\u{feff}:a=1\n:b=2\n:c=3\r\n",
+    ] {
+        assert_eq!(parse_program(source).lines.len(), 3, "{:?}", source);
+        assert_eq!(
+            globals(source, ChipLimits::GAME.unlimited(), 3),
+            ["a=1", "b=2", "c=3"],
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn blank_lines_are_kept() {
+    assert_eq!(parse_program("\n\n:a=1\n\n").lines.len(), 4);
+    assert_eq!(parse_program(" \t \n").lines.len(), 1);
+    assert_eq!(parse_program("").lines.len(), 1);
+    assert_eq!(parse_program("\r\n\r").lines.len(), 2);
+}